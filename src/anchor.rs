@@ -0,0 +1,132 @@
+//! Module for anchored searching: looking for an object that was not found at the place where found objects say it
+//! must be. If a found file `jal`s `__osSpDeviceBusy` at some address, the object defining it starts at that address
+//! less the symbol's offset, even if the object's stencil no longer matches exactly (version drift, patching).
+
+use crate::binary::Region;
+use crate::objfile::{SymbolKind, SymbolSection};
+use crate::sigs::Signature;
+use crate::symbols::Symbol;
+
+/// The fraction of an object's words that must match at its implied location for it to count as found.
+pub const ANCHORED_THRESHOLD: f64 = 0.9;
+
+/// How well a not-found object matched at the location implied by a reference to one of its symbols.
+#[derive(Debug, PartialEq)]
+pub struct Anchored {
+    pub name: String,
+    pub symbol: String,        // Symbol whose recovered address gave the location
+    pub referenced_by: String, // File it was recovered from
+    pub text_start: usize,
+    pub text_vram: u32,
+    pub score: f64,    // Fraction of words that match
+    pub demoted: bool, // Found, but overlapped a found file that was kept over it
+}
+
+impl Anchored {
+    pub fn is_found(&self) -> bool {
+        self.score >= ANCHORED_THRESHOLD
+    }
+}
+
+/// Check an object at the location implied by the first symbol of its .text that was recovered from a reference.
+pub fn anchored_search(
+    regions: &[Region],
+    signature: &Signature,
+    recovered: &[Symbol],
+) -> Option<Anchored> {
+    let obj = &signature.obj;
+    let text_index = obj
+        .sections
+        .iter()
+        .position(|section| section.name == ".text")?;
+
+    let (defined, reference) = obj
+        .symbols
+        .iter()
+        .filter(|symbol| {
+            symbol.kind == SymbolKind::Text && symbol.section == SymbolSection::Section(text_index)
+        })
+        .find_map(|defined| {
+            // A HI16 without its LO16 only gives half an address
+            let reference = recovered.iter().find(|symbol| {
+                symbol.is_complete() && !symbol.defined && symbol.name == defined.name
+            })?;
+            Some((defined, reference))
+        })?;
+    let text_vram = reference.address.wrapping_sub(defined.address);
+
+    let stencil = &signature.stencil;
+    if stencil.is_empty() {
+        return None;
+    }
+    let region = regions.iter().find(|region| {
+        region.endian == obj.endian
+            && text_vram >= region.vram
+            && (text_vram - region.vram) % 4 == 0
+            && ((text_vram - region.vram) as usize / 4) + stencil.len() <= region.words.len()
+    })?;
+    let index = (text_vram - region.vram) as usize / 4;
+
+    let matching = stencil
+        .iter()
+        .zip(&region.words[index..])
+        .filter(|(instr, word)| *word & instr.mask == instr.word)
+        .count();
+
+    Some(Anchored {
+        name: obj.name.clone(),
+        symbol: defined.name.clone(),
+        referenced_by: reference.filename.clone(),
+        text_start: region.offset + index * 4,
+        text_vram,
+        score: matching as f64 / stencil.len() as f64,
+        demoted: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::Endian;
+    use crate::objfile::ObjBuilder;
+
+    #[test]
+    fn found_where_referenced() {
+        let text = [
+            0x27BDFFE8, 0xAFBF0014, 0x24040001, 0x24050002, 0x00851021, 0x8FBF0014, 0x03E00008,
+            0x27BD0018, 0, 0,
+        ];
+        let obj = ObjBuilder::new("callee")
+            .text(&text)
+            .symbol("callee", SymbolSection::Section(0), 0, 0x28)
+            .build();
+        let signature = Signature::new(obj, None, None).unwrap();
+        let region = |words: &[u32]| Region {
+            offset: 0x1000,
+            vram: 0x80000400,
+            endian: Endian::Big,
+            words: [&[0; 4], words].concat(),
+        };
+        let reference = Symbol::new("callee", 0x80000410, 0, "caller", false);
+
+        let result = anchored_search(&[region(&text)], &signature, &[reference]).unwrap();
+        assert_eq!((result.text_start, result.text_vram), (0x1010, 0x80000410));
+        assert_eq!(result.referenced_by, "caller");
+        assert!(result.is_found());
+
+        // 8 of 10 words
+        let mut patched = text;
+        patched[2] = 0x24040003;
+        patched[3] = 0x24050004;
+        let reference = Symbol::new("callee", 0x80000410, 0, "caller", false);
+        let result = anchored_search(&[region(&patched)], &signature, &[reference]).unwrap();
+        assert_eq!(result.score, 0.8);
+        assert!(!result.is_found());
+
+        let reference = Symbol::new("callee", 0x80000410, 0, "caller", false).without_lo16();
+        assert_eq!(
+            anchored_search(&[region(&text)], &signature, &[reference]),
+            None
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Module for reading the binaries we search in. Each kind of binary is turned into a list of regions of known vram,
+//! which are what the search actually runs over.
+
+use std::error::Error;
+
+use clap::ValueEnum;
+use object::{Endianness, Object, ObjectSegment};
+
+use crate::words_from_bytes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BinaryKind {
+    /// N64 ROM image: the code after the IPL3 is searched, loaded at the header entrypoint
+    Rom,
+    /// Contiguous binary blob loaded at a known vram
+    Blob,
+    /// Linked ELF executable: every loadable segment is searched at its own vram
+    Elf,
+    /// PlayStation executable
+    Psexe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl From<Endianness> for Endian {
+    fn from(endianness: Endianness) -> Self {
+        match endianness {
+            Endianness::Big => Endian::Big,
+            Endianness::Little => Endian::Little,
+        }
+    }
+}
+
+/// Part of the binary that is loaded contiguously.
+#[derive(Debug)]
+pub struct Region {
+    pub offset: usize, // Offset of the start in the binary file
+    pub vram: u32,     // Address of the start once loaded
+    pub endian: Endian,
+    pub words: Vec<u32>,
+}
+
+impl Region {
+    fn new(data: &[u8], offset: usize, vram: u32, endian: Endian) -> Self {
+        let mut words = Vec::new();
+        words_from_bytes(data, endian, &mut words);
+        Region {
+            offset,
+            vram,
+            endian,
+            words,
+        }
+    }
+
+    /// The vram of an offset in the binary, if it is in this region.
+    pub fn vram_of(&self, offset: usize) -> Option<u32> {
+        let start = self.offset;
+        let end = start + self.words.len() * 4;
+        (start..end)
+            .contains(&offset)
+            .then(|| self.vram + (offset - start) as u32)
+    }
+
+    /// The offset in the binary of a vram address, if it is in this region.
+    pub fn offset_of(&self, vram: u32) -> Option<usize> {
+        let end = self.vram as u64 + self.words.len() as u64 * 4;
+        (self.vram as u64..end)
+            .contains(&(vram as u64))
+            .then(|| self.offset + (vram - self.vram) as usize)
+    }
+}
+
+const ROM_CODE_START: usize = 0x1000;
+// The boot segment size is not in the header, so use the most the IPL3 will load.
+const ROM_CODE_SIZE: usize = 0x100000;
+const ROM_ENTRYPOINT_OFFSET: usize = 0x8;
+
+/// Read the entrypoint from a (big-endian) ROM header.
+pub fn rom_entrypoint(rom: &[u8]) -> Option<u32> {
+    let bytes = rom.get(ROM_ENTRYPOINT_OFFSET..ROM_ENTRYPOINT_OFFSET + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// The boot segment of an N64 ROM. If `vram` is not given, the header entrypoint is used.
+pub fn rom_regions(rom: &[u8], vram: Option<u32>) -> Result<Vec<Region>, Box<dyn Error>> {
+    let vram = vram
+        .or_else(|| rom_entrypoint(rom))
+        .ok_or("ROM is too small to contain a header")?;
+    if rom.len() <= ROM_CODE_START {
+        return Err("ROM is too small to contain any code".into());
+    }
+    let end = rom.len().min(ROM_CODE_START + ROM_CODE_SIZE);
+
+    Ok(vec![Region::new(
+        &rom[ROM_CODE_START..end],
+        ROM_CODE_START,
+        vram,
+        Endian::Big,
+    )])
+}
+
+/// A whole file loaded at `vram`.
+pub fn blob_regions(data: &[u8], vram: u32, endian: Endian) -> Vec<Region> {
+    vec![Region::new(data, 0, vram, endian)]
+}
+
+/// The loadable segments of an ELF executable, at the addresses the program headers give them.
+pub fn elf_regions(data: &[u8]) -> Result<Vec<Region>, Box<dyn Error>> {
+    let elf_file = object::File::parse(data)?;
+    let endian = elf_file.endianness().into();
+    let mut regions = Vec::new();
+
+    for segment in elf_file.segments() {
+        let segment_data = segment.data()?;
+        if segment_data.is_empty() {
+            continue;
+        }
+        regions.push(Region::new(
+            segment_data,
+            segment.file_range().0 as usize,
+            segment.address() as u32,
+            endian,
+        ));
+    }
+
+    if regions.is_empty() {
+        return Err("ELF file has no loadable segments".into());
+    }
+    Ok(regions)
+}
+
+const PSEXE_MAGIC: &[u8] = b"PS-X EXE";
+const PSEXE_HEADER_SIZE: usize = 0x800;
+const PSEXE_TEXT_ADDRESS_OFFSET: usize = 0x18;
+const PSEXE_TEXT_SIZE_OFFSET: usize = 0x1C;
+
+/// The text segment of a PS-X EXE, which is everything after the header.
+pub fn psexe_regions(data: &[u8]) -> Result<Vec<Region>, Box<dyn Error>> {
+    if !data.starts_with(PSEXE_MAGIC) || data.len() < PSEXE_HEADER_SIZE {
+        return Err("not a PS-X EXE".into());
+    }
+    let read_word =
+        |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let vram = read_word(PSEXE_TEXT_ADDRESS_OFFSET);
+    let size = read_word(PSEXE_TEXT_SIZE_OFFSET) as usize;
+    let end = data.len().min(PSEXE_HEADER_SIZE + size);
+
+    Ok(vec![Region::new(
+        &data[PSEXE_HEADER_SIZE..end],
+        PSEXE_HEADER_SIZE,
+        vram,
+        Endian::Little,
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_boundaries() {
+        let region = Region::new(&[0; 0x10], 0x1000, 0x80000400, Endian::Big);
+        assert_eq!(region.vram_of(0xFFF), None);
+        assert_eq!(region.vram_of(0x1000), Some(0x80000400));
+        assert_eq!(region.vram_of(0x100F), Some(0x8000040F));
+        assert_eq!(region.vram_of(0x1010), None);
+        assert_eq!(region.offset_of(0x800003FF), None);
+        assert_eq!(region.offset_of(0x80000400), Some(0x1000));
+        assert_eq!(region.offset_of(0x8000040F), Some(0x100F));
+        assert_eq!(region.offset_of(0x80000410), None);
+
+        // Ending at the top of the address space must not overflow
+        let region = Region::new(&[0; 8], 0, 0xFFFFFFF8, Endian::Big);
+        assert_eq!(region.offset_of(0xFFFFFFFF), Some(7));
+        assert_eq!(region.vram_of(7), Some(0xFFFFFFFF));
+    }
+
+    #[test]
+    fn regions_of_each_kind() {
+        // ROM: the code after the IPL3, at the header entrypoint unless given
+        let mut rom = vec![0; 0x1010];
+        rom[8..12].copy_from_slice(&0x80000400u32.to_be_bytes());
+        rom[0x1000..0x1004].copy_from_slice(&0x27BDFFE8u32.to_be_bytes());
+        let regions = rom_regions(&rom, None).unwrap();
+        assert_eq!((regions[0].offset, regions[0].vram), (0x1000, 0x80000400));
+        assert_eq!(regions[0].words, [0x27BDFFE8, 0, 0, 0]);
+        assert_eq!(
+            rom_regions(&rom, Some(0x80001000)).unwrap()[0].vram,
+            0x80001000
+        );
+        assert!(rom_regions(&rom[..0x1000], None).is_err());
+
+        // Blob: the whole file, in the byte order given
+        let regions = blob_regions(&[0xE8, 0xFF, 0xBD, 0x27], 0x80010000, Endian::Little);
+        assert_eq!((regions[0].offset, regions[0].vram), (0, 0x80010000));
+        assert_eq!(regions[0].words, [0x27BDFFE8]);
+
+        // ELF: one loadable segment, at the file offset and address its program header gives
+        let mut elf = vec![0; 0x60];
+        elf[..7].copy_from_slice(b"\x7FELF\x01\x02\x01");
+        elf[0x10..0x14].copy_from_slice(&[0, 2, 0, 8]); // Executable, MIPS
+        elf[0x14..0x18].copy_from_slice(&1u32.to_be_bytes());
+        elf[0x1C..0x20].copy_from_slice(&0x34u32.to_be_bytes()); // Program headers
+        elf[0x28..0x2A].copy_from_slice(&0x34u16.to_be_bytes());
+        elf[0x2A..0x2C].copy_from_slice(&0x20u16.to_be_bytes());
+        elf[0x2C..0x2E].copy_from_slice(&1u16.to_be_bytes());
+        elf[0x2E..0x30].copy_from_slice(&0x28u16.to_be_bytes());
+        for (i, value) in [1, 0x58, 0x80000400, 0x80000400, 8, 8, 5, 4]
+            .into_iter()
+            .enumerate()
+        {
+            elf[0x34 + i * 4..0x38 + i * 4].copy_from_slice(&u32::to_be_bytes(value));
+        }
+        elf[0x58..0x5C].copy_from_slice(&0x27BDFFE8u32.to_be_bytes());
+        let regions = elf_regions(&elf).unwrap();
+        assert_eq!((regions[0].offset, regions[0].vram), (0x58, 0x80000400));
+        assert_eq!(regions[0].words, [0x27BDFFE8, 0]);
+
+        // PS-X EXE: the text after the header, at the address the header gives
+        let mut psexe = vec![0; 0x810];
+        psexe[..8].copy_from_slice(PSEXE_MAGIC);
+        psexe[0x18..0x1C].copy_from_slice(&0x80010000u32.to_le_bytes());
+        psexe[0x1C..0x20].copy_from_slice(&8u32.to_le_bytes());
+        psexe[0x800..0x804].copy_from_slice(&0x27BDFFE8u32.to_le_bytes());
+        let regions = psexe_regions(&psexe).unwrap();
+        assert_eq!((regions[0].offset, regions[0].vram), (0x800, 0x80010000));
+        assert_eq!(regions[0].words, [0x27BDFFE8, 0]);
+        assert!(psexe_regions(&psexe[..0x10]).is_err());
+    }
+}
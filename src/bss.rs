@@ -0,0 +1,186 @@
+//! Module for working out where each object's .bss (and GCC's .sbss) went. Nothing of .bss is in the binary, but found
+//! code refers to it: to its own .bss through the section symbol, and to other files' .bss globals by name. Each
+//! reference gives the exact address of the .bss it points into, less the offset of what it points to.
+
+use crate::first_max_by_key;
+use crate::objfile::{SectionKind, SymbolSection};
+use crate::sigs::Signature;
+use crate::symbols::Symbol;
+
+/// Where one reference puts an object's .bss.
+#[derive(Debug, PartialEq)]
+pub struct BssReference {
+    pub referenced_by: String,
+    pub symbol: String,
+    pub start: u32,
+}
+
+/// Where an object's .bss starts, and the references that say so.
+#[derive(Debug, PartialEq)]
+pub struct BssLayout {
+    pub name: String,
+    pub section: usize, // Index of the object's .bss (or .sbss)
+    pub section_name: String,
+    pub start: u32,
+    pub size: u32,
+    pub agreeing: Vec<BssReference>,
+    pub disagreeing: Vec<BssReference>, // References that put it somewhere else
+    pub overlaps: Vec<String>, // Other objects whose .bss would share addresses with this one
+}
+
+impl BssLayout {
+    pub fn end(&self) -> u32 {
+        self.start + self.size
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.disagreeing.is_empty() && self.overlaps.is_empty()
+    }
+}
+
+/// Where references in `recovered` put the .bss of each of `signatures` that has one. Where references disagree, the
+/// start most of them give is used (the first given, if tied).
+pub fn infer(signatures: &[&Signature], recovered: &[Symbol]) -> Vec<BssLayout> {
+    let mut layouts = Vec::new();
+
+    let sections = signatures.iter().flat_map(|signature| {
+        signature
+            .obj
+            .sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.kind == SectionKind::Bss && section.size > 0)
+            .map(move |(index, section)| (&signature.obj, index, section))
+    });
+    for (obj, index, section) in sections {
+        let mut references = Vec::new();
+        for symbol in recovered.iter().filter(|symbol| symbol.is_complete()) {
+            // Section symbols are local, so only the object itself refers to its .bss by them
+            let offset = if symbol.name == section.name && symbol.filename == obj.name {
+                Some(0)
+            } else {
+                obj.symbols
+                    .iter()
+                    .find(|defined| {
+                        defined.name == symbol.name
+                            && defined.global
+                            && defined.section == SymbolSection::Section(index)
+                    })
+                    .map(|defined| defined.address)
+            };
+            if let Some(offset) = offset {
+                references.push(BssReference {
+                    referenced_by: symbol.filename.clone(),
+                    symbol: symbol.name.clone(),
+                    start: symbol.address.wrapping_sub(offset),
+                });
+            }
+        }
+        if references.is_empty() {
+            continue;
+        }
+
+        let starts = references
+            .iter()
+            .map(|reference| reference.start)
+            .collect::<Vec<_>>();
+        let start = first_max_by_key(starts.iter().copied(), |&start| {
+            references.iter().filter(|r| r.start == start).count()
+        })
+        .unwrap();
+        let (agreeing, disagreeing) = references
+            .into_iter()
+            .partition(|reference| reference.start == start);
+
+        layouts.push(BssLayout {
+            name: obj.name.clone(),
+            section: index,
+            section_name: section.name.clone(),
+            start,
+            size: section.size,
+            agreeing,
+            disagreeing,
+            overlaps: Vec::new(),
+        });
+    }
+
+    layouts.sort_by_key(|layout| layout.start);
+    for i in 0..layouts.len() {
+        for j in i + 1..layouts.len() {
+            if layouts[j].start >= layouts[i].end() {
+                break;
+            }
+            let (first, second) = (layouts[i].name.clone(), layouts[j].name.clone());
+            layouts[i].overlaps.push(second);
+            layouts[j].overlaps.push(first);
+        }
+    }
+    layouts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::ObjBuilder;
+
+    #[test]
+    fn starts_from_references() {
+        let a = ObjBuilder::new("a")
+            .text(&[1])
+            .section(".bss", SectionKind::Bss, &[0; 0x20])
+            .section_symbol(1)
+            .symbol("a_buf", SymbolSection::Section(1), 0x10, 0x10)
+            .build();
+        let b = ObjBuilder::new("b")
+            .section(".bss", SectionKind::Bss, &[0; 0x10])
+            .symbol("b_buf", SymbolSection::Section(0), 0, 0x10)
+            .build();
+        let signatures = [a, b].map(|obj| Signature::new(obj, None, None).unwrap());
+        let reference = |name: &str, address, filename: &str| BssReference {
+            referenced_by: filename.to_string(),
+            symbol: name.to_string(),
+            start: address,
+        };
+
+        let recovered = [
+            Symbol::new(".bss", 0x80100000, 0, "a", false),
+            Symbol::new("a_buf", 0x80100010, 0, "c", false),
+            Symbol::new("a_buf", 0x80100110, 0, "d", false),
+            // Another file's own .bss
+            Symbol::new(".bss", 0x80200000, 0, "c", false),
+            // Overlaps the end of a's .bss
+            Symbol::new("b_buf", 0x80100018, 0, "c", false),
+            Symbol::new("b_buf", 0x80300000, 0, "d", false).without_lo16(),
+        ];
+        let layouts = infer(&signatures.iter().collect::<Vec<_>>(), &recovered);
+        assert_eq!(
+            layouts,
+            [
+                BssLayout {
+                    name: "a".to_string(),
+                    section: 1,
+                    section_name: ".bss".to_string(),
+                    start: 0x80100000,
+                    size: 0x20,
+                    agreeing: vec![
+                        reference(".bss", 0x80100000, "a"),
+                        reference("a_buf", 0x80100000, "c"),
+                    ],
+                    disagreeing: vec![reference("a_buf", 0x80100100, "d")],
+                    overlaps: vec!["b".to_string()],
+                },
+                BssLayout {
+                    name: "b".to_string(),
+                    section: 0,
+                    section_name: ".bss".to_string(),
+                    start: 0x80100018,
+                    size: 0x10,
+                    agreeing: vec![reference("b_buf", 0x80100018, "c")],
+                    disagreeing: Vec::new(),
+                    overlaps: vec!["a".to_string()],
+                },
+            ]
+        );
+        assert!(layouts.iter().all(|layout| !layout.is_consistent()));
+    }
+}
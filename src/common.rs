@@ -0,0 +1,246 @@
+//! Module for COMMON symbols and small data. Neither has a place in the objects: COMMON symbols are only declared, and
+//! are laid out by the linker, and small data is addressed relative to $gp. References from found code give their
+//! addresses all the same, and the order they come in is the order to reproduce in a linker script.
+
+use crate::first_max_by_key;
+use crate::objfile::SymbolSection;
+use crate::sigs::Signature;
+use crate::symbols::{GpReference, Symbol};
+
+/// Sections GCC keeps small data in.
+const SMALL_DATA_SECTIONS: &[&str] = &[".sdata", ".sbss", ".scommon", ".lit4", ".lit8"];
+
+/// A COMMON or small-data symbol, and where references put it.
+#[derive(Debug, PartialEq)]
+pub struct AreaSymbol {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,                  // Largest declared
+    pub defined_in: Option<String>, // None for COMMON symbols
+    pub small: bool,                // Addressed relative to $gp
+    pub referenced_by: Vec<String>,
+    pub disagreeing: Vec<(String, u32)>, // Files that put it somewhere else, and where
+}
+
+impl AreaSymbol {
+    pub fn end(&self) -> u32 {
+        self.address + self.size
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CommonArea {
+    pub gp: Option<u32>,
+    pub symbols: Vec<AreaSymbol>, // In address order
+    pub unplaced: Vec<String>,    // Declared, but never referenced by found code
+}
+
+fn is_section_symbol(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// Work out $gp from symbols that are referenced both relative to it and by address. Gives the value most of them
+/// agree on, and how many do.
+pub fn infer_gp(references: &[GpReference], recovered: &[Symbol]) -> Option<(u32, usize)> {
+    let mut values: Vec<(u32, usize)> = Vec::new();
+    for reference in references {
+        let absolute = recovered.iter().find(|symbol| {
+            symbol.is_complete()
+                && symbol.name == reference.name
+                // Section symbols are local to their file
+                && (!is_section_symbol(&symbol.name) || symbol.filename == reference.filename)
+        });
+        let Some(absolute) = absolute else {
+            continue;
+        };
+        let gp = absolute.address.wrapping_sub(reference.offset as u32);
+        match values.iter_mut().find(|(value, _)| *value == gp) {
+            Some((_, count)) => *count += 1,
+            None => values.push((gp, 1)),
+        }
+    }
+    first_max_by_key(values, |(_, count)| *count)
+}
+
+/// The addresses references relative to $gp point to, once $gp is known.
+pub fn resolve_gp(references: &[GpReference], gp: u32) -> Vec<Symbol> {
+    references
+        .iter()
+        .map(|reference| {
+            Symbol::new(
+                &reference.name,
+                gp.wrapping_add(reference.offset as u32),
+                0,
+                &reference.filename,
+                false,
+            )
+        })
+        .collect()
+}
+
+/// Collect the COMMON symbols declared by `signatures` and the small-data symbols they define, and place each where
+/// the references to it in `recovered` put it.
+pub fn infer(
+    signatures: &[&Signature],
+    recovered: &[Symbol],
+    gp_relative: &[GpReference],
+    gp: Option<u32>,
+) -> CommonArea {
+    // Name, largest size, defining file and whether it is small
+    let mut declared: Vec<(String, u32, Option<String>, bool)> = Vec::new();
+    for signature in signatures {
+        let obj = &signature.obj;
+        for symbol in &obj.symbols {
+            let (defined_in, small) = match symbol.section {
+                SymbolSection::Common => (None, false),
+                SymbolSection::Section(index)
+                    if symbol.global
+                        && symbol.is_definition()
+                        && SMALL_DATA_SECTIONS.contains(&obj.sections[index].name.as_str()) =>
+                {
+                    (Some(obj.name.clone()), true)
+                }
+                _ => continue,
+            };
+            match declared.iter_mut().find(|(name, ..)| name == &symbol.name) {
+                Some(entry) => {
+                    entry.1 = entry.1.max(symbol.size);
+                    if defined_in.is_some() {
+                        entry.2 = defined_in;
+                    }
+                    entry.3 |= small;
+                }
+                None => declared.push((symbol.name.clone(), symbol.size, defined_in, small)),
+            }
+        }
+    }
+
+    let mut area = CommonArea {
+        gp,
+        ..Default::default()
+    };
+    for (name, size, defined_in, small) in declared {
+        let references = recovered
+            .iter()
+            .filter(|symbol| symbol.is_complete() && symbol.name == name)
+            .collect::<Vec<_>>();
+        let addresses = references.iter().map(|symbol| symbol.address);
+        let Some(address) = first_max_by_key(addresses, |&address| {
+            references.iter().filter(|r| r.address == address).count()
+        }) else {
+            area.unplaced.push(name);
+            continue;
+        };
+
+        let mut referenced_by = Vec::new();
+        let mut disagreeing = Vec::new();
+        for symbol in references {
+            if symbol.address != address {
+                disagreeing.push((symbol.filename.clone(), symbol.address));
+            } else if !referenced_by.contains(&symbol.filename) {
+                referenced_by.push(symbol.filename.clone());
+            }
+        }
+        let small = small || gp_relative.iter().any(|reference| reference.name == name);
+        area.symbols.push(AreaSymbol {
+            name,
+            address,
+            size,
+            defined_in,
+            small,
+            referenced_by,
+            disagreeing,
+        });
+    }
+    area.symbols.sort_by_key(|symbol| symbol.address);
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::{ObjBuilder, SectionKind};
+
+    #[test]
+    fn gp_from_absolute_references() {
+        let reference = |name: &str, offset| GpReference {
+            name: name.to_string(),
+            filename: "a".to_string(),
+            offset,
+        };
+        let references = [reference("count", -0x7FF0), reference("flag", 0x10)];
+        let recovered = [
+            Symbol::new("count", 0x80010000, 0, "b", false),
+            Symbol::new("flag", 0x80018000, 0, "c", false),
+        ];
+        assert_eq!(infer_gp(&references, &recovered), Some((0x80017FF0, 2)));
+        assert_eq!(
+            resolve_gp(&references, 0x80017FF0)[1],
+            Symbol::new("flag", 0x80018000, 0, "a", false)
+        );
+    }
+
+    #[test]
+    fn placed_by_references() {
+        let a = ObjBuilder::new("a")
+            .symbol("buf", SymbolSection::Common, 0, 0x10)
+            .symbol("counter", SymbolSection::Common, 0, 4)
+            .build();
+        let b = ObjBuilder::new("b")
+            .text(&[1])
+            .section(".sdata", SectionKind::Data, &[0; 8])
+            .symbol("buf", SymbolSection::Common, 0, 0x40)
+            .symbol("flag", SymbolSection::Section(1), 0, 4)
+            .build();
+        let c = ObjBuilder::new("c")
+            .symbol("unused", SymbolSection::Common, 0, 4)
+            .build();
+        let signatures = [a, b, c].map(|obj| Signature::new(obj, None, None).unwrap());
+
+        let gp = 0x80208030;
+        let gp_relative = [GpReference {
+            name: "flag".to_string(),
+            filename: "a".to_string(),
+            offset: -0x7FF0,
+        }];
+        let mut recovered = vec![
+            Symbol::new("buf", 0x80200000, 0, "a", false),
+            Symbol::new("buf", 0x80200000, 0, "b", false),
+            Symbol::new("buf", 0x80200100, 0, "d", false),
+            Symbol::new("counter", 0x80200044, 0, "a", false),
+        ];
+        recovered.extend(resolve_gp(&gp_relative, gp));
+
+        let area = infer(
+            &signatures.iter().collect::<Vec<_>>(),
+            &recovered,
+            &gp_relative,
+            Some(gp),
+        );
+        let symbol = |name: &str, address, size, defined_in: Option<&str>, small| AreaSymbol {
+            name: name.to_string(),
+            address,
+            size,
+            defined_in: defined_in.map(String::from),
+            small,
+            referenced_by: vec!["a".to_string()],
+            disagreeing: Vec::new(),
+        };
+        assert_eq!(
+            area,
+            CommonArea {
+                gp: Some(gp),
+                symbols: vec![
+                    AreaSymbol {
+                        referenced_by: vec!["a".to_string(), "b".to_string()],
+                        disagreeing: vec![("d".to_string(), 0x80200100)],
+                        ..symbol("buf", 0x80200000, 0x40, None, false)
+                    },
+                    symbol("flag", 0x80200040, 4, Some("b"), true),
+                    symbol("counter", 0x80200044, 4, None, false),
+                ],
+                unplaced: vec!["unused".to_string()],
+            }
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Module for scoring how much a match should be believed. A three-word getter matches in plenty of places it was
+//! never linked, whereas a 4 KiB `xprintf` only matches where it really is, and the score reflects that, along with
+//! whether the addresses recovered from the match make sense and whether other found files agree.
+
+use std::collections::HashSet;
+
+use crate::objfile::{SymbolKind, SymbolSection};
+use crate::sigs::Signature;
+use crate::symbols::Symbol;
+
+/// Number of effective bits that gives a match a score of a half before anything else is considered.
+const HALF_CONFIDENCE_BITS: f64 = 64.0;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Confidence {
+    pub words: usize,
+    pub bits: u32,      // Bits of the binary the stencil checks
+    pub diversity: f64, // Distinct masked words as a fraction of all of them
+    pub checked: usize, // Recovered addresses that can be checked against the object's own layout
+    pub consistent: usize,
+    pub corroborating: usize, // References from other found files that land on the object's symbols
+    pub anchor_score: Option<f64>, // Fraction of words that matched, if the match was anchored
+}
+
+impl Confidence {
+    /// Overall score between 0 and 1.
+    pub fn score(&self) -> f64 {
+        let effective_bits = self.bits as f64 * self.diversity;
+        let mut score = effective_bits / (effective_bits + HALF_CONFIDENCE_BITS);
+
+        if self.checked > 0 {
+            score *= self.consistent as f64 / self.checked as f64;
+        }
+        if let Some(anchor_score) = self.anchor_score {
+            score *= anchor_score;
+        }
+        // Each corroborating reference halves the doubt
+        1.0 - (1.0 - score) * 0.5f64.powi(self.corroborating as i32)
+    }
+}
+
+/// Where the object's own layout puts a symbol of its .text, or the .text itself, if it is at `text_vram`.
+fn expected_address(signature: &Signature, text_vram: u32, symbol_name: &str) -> Option<u32> {
+    let obj = &signature.obj;
+    if symbol_name == ".text" {
+        return Some(text_vram);
+    }
+    let text_index = obj
+        .sections
+        .iter()
+        .position(|section| section.name == ".text")?;
+    obj.symbols
+        .iter()
+        .find(|symbol| {
+            symbol.name == symbol_name
+                && symbol.kind == SymbolKind::Text
+                && symbol.section == SymbolSection::Section(text_index)
+        })
+        .map(|symbol| text_vram.wrapping_add(symbol.address))
+}
+
+/// Check the addresses recovered from one match's relocations against where the object itself says its .text and
+/// functions are, giving how many could be checked and how many were right.
+pub fn check_relocated(
+    signature: &Signature,
+    text_vram: u32,
+    relocated: &[Symbol],
+) -> (usize, usize) {
+    let mut checked = 0;
+    let mut consistent = 0;
+    for symbol in relocated {
+        if let Some(expected) = expected_address(signature, text_vram, &symbol.name) {
+            checked += 1;
+            if symbol.address == expected {
+                consistent += 1;
+            }
+        }
+    }
+    (checked, consistent)
+}
+
+/// Score a match of an object whose .text was found at `text_vram`. `checks` is from `check_relocated`, and `symbols`
+/// are all those recovered from the found files.
+pub fn evaluate(
+    signature: &Signature,
+    text_vram: u32,
+    checks: (usize, usize),
+    symbols: &[Symbol],
+    anchor_score: Option<f64>,
+) -> Confidence {
+    let stencil = &signature.stencil;
+
+    let bits = stencil.iter().map(|instr| instr.mask.count_ones()).sum();
+    let distinct = stencil
+        .iter()
+        .map(|instr| (instr.word, instr.mask))
+        .collect::<HashSet<_>>()
+        .len();
+    let diversity = if stencil.is_empty() {
+        0.0
+    } else {
+        distinct as f64 / stencil.len() as f64
+    };
+
+    // References the other files make to this one, which only land on its symbols if both are where we think
+    let corroborating = symbols
+        .iter()
+        .filter(|symbol| {
+            !symbol.defined
+                && symbol.filename != signature.obj.name
+                && symbol.name != ".text"
+                && expected_address(signature, text_vram, &symbol.name) == Some(symbol.address)
+        })
+        .count();
+
+    Confidence {
+        words: stencil.len(),
+        bits,
+        diversity,
+        checked: checks.0,
+        consistent: checks.1,
+        corroborating,
+        anchor_score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longer_is_more_confident() {
+        let short = Confidence {
+            words: 3,
+            bits: 80,
+            diversity: 1.0,
+            ..Default::default()
+        };
+        let long = Confidence {
+            words: 1000,
+            bits: 30000,
+            diversity: 0.5,
+            ..Default::default()
+        };
+        assert!(short.score() < 0.6);
+        assert!(long.score() > 0.99);
+
+        let corroborated = Confidence {
+            corroborating: 2,
+            ..short.clone()
+        };
+        assert!(corroborated.score() > 0.85);
+
+        let inconsistent = Confidence {
+            checked: 2,
+            consistent: 1,
+            ..short
+        };
+        assert!(inconsistent.score() < 0.3);
+    }
+}
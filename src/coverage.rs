@@ -0,0 +1,164 @@
+//! Module for how much of each part of a library was found, and which of the missing files were probably linked in
+//! anyway, going by what the found files reference.
+
+use std::collections::HashMap;
+
+use crate::profile::Profiles;
+use crate::sigs::Signature;
+
+/// A file that was not found, but which defines a symbol that a found file uses.
+#[derive(Debug, PartialEq)]
+pub struct LikelyPresent {
+    pub name: String,
+    pub symbol: String,
+    pub referenced_by: String,
+}
+
+/// Files grouped by the directory of the library's source they come from, according to the profiles.
+#[derive(Debug, Default, PartialEq)]
+pub struct Subsystem {
+    pub name: String,
+    pub found: Vec<String>,
+    pub ambiguous: Vec<String>,
+    pub not_found: Vec<String>,
+}
+
+impl Subsystem {
+    /// The fraction of the files searched for that were located, even if ambiguously.
+    pub fn coverage(&self) -> f64 {
+        let located = self.found.len() + self.ambiguous.len();
+        located as f64 / (located + self.not_found.len()) as f64
+    }
+}
+
+/// Find the missing files that define global symbols which found files reference but do not define themselves.
+pub fn likely_present(found: &[&Signature], not_found: &[&Signature]) -> Vec<LikelyPresent> {
+    let mut referenced = HashMap::new();
+    for signature in found {
+        let obj = &signature.obj;
+        for section in &obj.sections {
+            for reloc in &section.relocations {
+                let symbol = &obj.symbols[reloc.symbol];
+                if !symbol.is_definition() && !symbol.name.is_empty() {
+                    referenced
+                        .entry(symbol.name.as_str())
+                        .or_insert(obj.name.as_str());
+                }
+            }
+        }
+    }
+
+    let mut likely = Vec::new();
+    for signature in not_found {
+        let obj = &signature.obj;
+        let reference = obj
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.is_definition() && symbol.global)
+            .find_map(|symbol| Some((symbol, referenced.get(symbol.name.as_str())?)));
+        if let Some((symbol, referenced_by)) = reference {
+            likely.push(LikelyPresent {
+                name: obj.name.clone(),
+                symbol: symbol.name.clone(),
+                referenced_by: referenced_by.to_string(),
+            });
+        }
+    }
+    likely
+}
+
+/// Group files by subsystem, with those no profile knows the directory of last, under "other".
+pub fn subsystems(
+    found: &[&str],
+    ambiguous: &[&str],
+    not_found: &[&str],
+    profiles: &Profiles,
+) -> Vec<Subsystem> {
+    let mut subsystems: Vec<Subsystem> = Vec::new();
+    let mut add = |name: &str, list: fn(&mut Subsystem) -> &mut Vec<String>| {
+        let directory = profiles
+            .file(name)
+            .and_then(|file| file.directory.as_deref())
+            .unwrap_or("other");
+        let index = match subsystems.iter().position(|sub| sub.name == directory) {
+            Some(index) => index,
+            None => {
+                subsystems.push(Subsystem {
+                    name: directory.to_string(),
+                    ..Default::default()
+                });
+                subsystems.len() - 1
+            }
+        };
+        list(&mut subsystems[index]).push(name.to_string());
+    };
+
+    for name in found {
+        add(name, |sub| &mut sub.found);
+    }
+    for name in ambiguous {
+        add(name, |sub| &mut sub.ambiguous);
+    }
+    for name in not_found {
+        add(name, |sub| &mut sub.not_found);
+    }
+
+    subsystems.sort_by(|a, b| (a.name == "other", &a.name).cmp(&(b.name == "other", &b.name)));
+    subsystems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::{ObjBuilder, RelocationKind, SymbolSection};
+    use crate::profile::Profile;
+
+    /// An object defining `name` in its .text, which is one `jal`.
+    fn object(name: &str) -> ObjBuilder {
+        ObjBuilder::new(name)
+            .text(&[0x0C000000])
+            .symbol(name, SymbolSection::Section(0), 0, 4)
+    }
+
+    #[test]
+    fn missing_definitions() {
+        let caller = object("caller")
+            .symbol("osCreateThread", SymbolSection::Undefined, 0, 0)
+            .reloc(".text", 0, RelocationKind::Mips26, "osCreateThread", 0)
+            .build();
+        let createthread = ObjBuilder::new("createthread")
+            .text(&[0x0C000000])
+            .symbol("osCreateThread", SymbolSection::Section(0), 0, 4)
+            .build();
+        let unused = object("unused").build();
+        let [caller, createthread, unused] =
+            [caller, createthread, unused].map(|obj| Signature::new(obj, None, None).unwrap());
+
+        let likely = likely_present(&[&caller], &[&createthread, &unused]);
+        assert_eq!(
+            likely,
+            [LikelyPresent {
+                name: "createthread".to_string(),
+                symbol: "osCreateThread".to_string(),
+                referenced_by: "caller".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn grouped_by_directory() {
+        let profiles = Profiles(vec![Profile::builtin("libgultra").unwrap()]);
+        let subsystems = subsystems(
+            &["createthread", "mine"],
+            &["cspdelete"],
+            &["auxbus", "sqrtf"],
+            &profiles,
+        );
+        let names = subsystems
+            .iter()
+            .map(|sub| sub.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["audio", "gu", "os", "other"]);
+        assert_eq!(subsystems[0].coverage(), 0.5);
+    }
+}
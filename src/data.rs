@@ -0,0 +1,343 @@
+//! Module for locating data-only objects, such as `vitbl` (osViModeTable) or `libm_vals`. They have no code to match,
+//! so their .data or .rodata is searched for instead. Zero-filled ones would match anywhere, so they are located where
+//! references from found code put their symbols.
+
+use crate::binary::Region;
+use crate::objfile::{ObjFile, RelocationKind, SectionKind, SymbolKind, SymbolSection};
+use crate::sigs::Signature;
+use crate::symbols::{self, Symbol};
+use crate::{first_max_by_key, precise_check, words_from_bytes, PreciseStencil, FULL_MASK};
+
+/// A data-only object that was located.
+#[derive(Debug, PartialEq)]
+pub struct FoundData {
+    pub name: String,
+    pub section: SectionKind, // Data or ReadOnlyData
+    pub start: usize,         // Offset in the binary
+    pub vram: u32,
+    pub size: usize,
+    pub referenced_by: Option<String>, // File whose reference located it, if its contents could not
+}
+
+/// Where a data-only object's contents matched.
+pub enum Located {
+    Found(FoundData),
+    Ambiguous(Vec<usize>),
+    NotFound,
+}
+
+/// Whether an object has no code to search for, i.e. its .text is missing, empty or all zeros.
+pub fn is_data_only(obj: &ObjFile) -> bool {
+    obj.section_by_name(".text")
+        .is_none_or(|text| text.data.iter().all(|byte| *byte == 0))
+}
+
+/// The section of a data-only object to look for: the largest of its .data and .rodata sections. Gives `None` if the
+/// object has code, or no such section.
+pub fn data_section(obj: &ObjFile) -> Option<usize> {
+    if !is_data_only(obj) {
+        return None;
+    }
+    // Of equal sizes, .data comes first
+    let sections = obj.sections.iter().enumerate().filter(|(_, section)| {
+        matches!(section.kind, SectionKind::Data | SectionKind::ReadOnlyData) && section.size >= 4
+    });
+    first_max_by_key(sections, |(_, section)| section.size).map(|(index, _)| index)
+}
+
+/// Like a .text stencil, but only pointers are relocated in data.
+pub fn make_data_stencil(obj: &ObjFile, index: usize) -> Vec<PreciseStencil> {
+    let section = &obj.sections[index];
+    let mut words = Vec::new();
+    words_from_bytes(&section.data, obj.endian, &mut words);
+
+    let mut stencil = words
+        .into_iter()
+        .map(|word| PreciseStencil {
+            word,
+            addend: word,
+            mask: FULL_MASK,
+        })
+        .collect::<Vec<_>>();
+    for reloc in &section.relocations {
+        if reloc.kind == RelocationKind::Word32 {
+            if let Some(instr) = stencil.get_mut(reloc.offset as usize / 4) {
+                instr.word = 0;
+                instr.mask = 0;
+            }
+        }
+    }
+    stencil
+}
+
+fn found_data(
+    signature: &Signature,
+    index: usize,
+    regions: &[Region],
+    start: usize,
+    referenced_by: Option<String>,
+) -> Option<FoundData> {
+    let section = &signature.obj.sections[index];
+    Some(FoundData {
+        name: signature.obj.name.clone(),
+        section: section.kind,
+        start,
+        vram: regions.iter().find_map(|region| region.vram_of(start))?,
+        size: section.size as usize,
+        referenced_by,
+    })
+}
+
+/// Search for the contents of a data-only object's data section `index`, at offsets whose vram is aligned to
+/// `alignment`. Zero-filled sections are not searched for, since they would match anywhere.
+pub fn search(regions: &[Region], signature: &Signature, index: usize, alignment: u32) -> Located {
+    let obj = &signature.obj;
+    let stencil = make_data_stencil(obj, index);
+    if stencil.iter().all(|instr| instr.word == 0) {
+        return Located::NotFound;
+    }
+
+    let mut offsets = Vec::new();
+    for region in regions {
+        if region.endian != obj.endian || region.words.len() < stencil.len() {
+            continue;
+        }
+        for i in 0..=region.words.len() - stencil.len() {
+            let vram = region.vram + i as u32 * 4;
+            if vram.is_multiple_of(alignment)
+                && precise_check(&region.words[i..i + stencil.len()], &stencil)
+            {
+                offsets.push(region.offset + i * 4);
+            }
+        }
+    }
+
+    match offsets.len() {
+        0 => Located::NotFound,
+        1 => match found_data(signature, index, regions, offsets[0], None) {
+            Some(found) => Located::Found(found),
+            None => Located::NotFound,
+        },
+        _ => Located::Ambiguous(offsets),
+    }
+}
+
+/// Locate data section `index` of an object where a reference from another file puts one of its symbols. If its
+/// contents matched anywhere, it must be at one of those `candidates`.
+pub fn locate_by_reference(
+    regions: &[Region],
+    signature: &Signature,
+    index: usize,
+    candidates: &[usize],
+    recovered: &[Symbol],
+) -> Option<FoundData> {
+    let obj = &signature.obj;
+    obj.symbols
+        .iter()
+        .filter(|symbol| symbol.section == SymbolSection::Section(index) && symbol.is_definition())
+        .find_map(|defined| {
+            let reference = recovered
+                .iter()
+                .find(|symbol| symbol.name == defined.name && symbol.filename != obj.name)?;
+            let vram = reference.address.wrapping_sub(defined.address);
+            let start = regions.iter().find_map(|region| region.offset_of(vram))?;
+            if !candidates.is_empty() && !candidates.contains(&start) {
+                return None;
+            }
+            found_data(
+                signature,
+                index,
+                regions,
+                start,
+                Some(reference.filename.clone()),
+            )
+        })
+}
+
+/// Where references in `recovered` put data section `index` of an object: the object's own references through the
+/// section symbol or to the symbols in it, or other files' references to its globals.
+pub fn section_vram(signature: &Signature, index: usize, recovered: &[Symbol]) -> Option<u32> {
+    let obj = &signature.obj;
+    let section = &obj.sections[index];
+    recovered
+        .iter()
+        .filter(|symbol| symbol.is_complete())
+        .find_map(|symbol| {
+            if symbol.name == section.name && symbol.filename == obj.name {
+                return Some(symbol.address);
+            }
+            let defined = obj.symbols.iter().find(|defined| {
+                defined.name == symbol.name
+                    && defined.section == SymbolSection::Section(index)
+                    && defined.is_definition()
+                    // Statics are only referred to by the object itself
+                    && (defined.global || symbol.filename == obj.name)
+            })?;
+            Some(symbol.address.wrapping_sub(defined.address))
+        })
+}
+
+/// The addresses pointers in data section `index` of an object give, with the section at offset `start` in the
+/// binary. Gives `None` if the section's contents are not there.
+pub fn parse_pointers(
+    regions: &[Region],
+    signature: &Signature,
+    index: usize,
+    start: usize,
+) -> Option<Vec<Symbol>> {
+    let obj = &signature.obj;
+    let stencil = make_data_stencil(obj, index);
+    let region = regions
+        .iter()
+        .find(|region| region.vram_of(start).is_some())?;
+    let first = (start - region.offset) / 4;
+    let rom_words = region.words.get(first..first + stencil.len())?;
+    if region.endian != obj.endian || !precise_check(rom_words, &stencil) {
+        return None;
+    }
+
+    let mut pointers = Vec::new();
+    for reloc in &obj.sections[index].relocations {
+        let word = reloc.offset as usize / 4;
+        if reloc.kind != RelocationKind::Word32 || word >= rom_words.len() {
+            continue;
+        }
+        let symbol = &obj.symbols[reloc.symbol];
+        // What was in the field before relocation is the addend
+        let addend = stencil[word].addend.wrapping_add(reloc.addend as u32);
+        let address = rom_words[word].wrapping_sub(addend);
+        match symbol.section {
+            // Pointers into the object itself, e.g. jump tables, go through section symbols
+            SymbolSection::Section(target) if symbol.kind == SymbolKind::Section => {
+                let (name, offset, size) =
+                    symbols::name_in_section(obj, target, addend, rom_words[word]);
+                pointers.push(Symbol::new(
+                    &name,
+                    address.wrapping_add(offset),
+                    size,
+                    &obj.name,
+                    true,
+                ));
+            }
+            _ => pointers.push(Symbol::new(
+                &symbol.name,
+                address,
+                symbol.size,
+                &obj.name,
+                symbol.is_definition(),
+            )),
+        }
+    }
+    Some(pointers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::Endian;
+    use crate::objfile::ObjBuilder;
+
+    #[test]
+    fn largest_data_section() {
+        let obj = |text: &[u32]| {
+            ObjBuilder::new("vitbl")
+                .text(text)
+                .section(".data", SectionKind::Data, &[1; 0x50])
+                .section(".rodata", SectionKind::ReadOnlyData, &[1; 0x10])
+                .build()
+        };
+        assert_eq!(data_section(&obj(&[])), Some(1));
+        assert_eq!(data_section(&obj(&[1, 1])), None);
+    }
+
+    fn region(words: &[u32]) -> Region {
+        Region {
+            offset: 0x1000,
+            vram: 0x80000400,
+            endian: Endian::Big,
+            words: words.to_vec(),
+        }
+    }
+
+    fn bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn located_by_contents() {
+        let contents = [0x11111111, 0x22222222, 0x33333333];
+        let obj = ObjBuilder::new("vitbl")
+            .section(".data", SectionKind::Data, &bytes(&contents))
+            .build();
+        let signature = Signature::new(obj, None, None).unwrap();
+        let regions = [region(
+            &[[0, 0].as_slice(), &contents, &[0; 3], &contents, &[0; 2]].concat(),
+        )];
+
+        assert!(
+            matches!(search(&regions, &signature, 0, 4), Located::Ambiguous(offsets) if offsets == [0x1008, 0x1020])
+        );
+        // Only the second copy is aligned to 16 bytes
+        let Located::Found(found) = search(&regions, &signature, 0, 0x10) else {
+            panic!("the aligned copy was not found");
+        };
+        assert_eq!(
+            (found.start, found.vram, found.size),
+            (0x1020, 0x80000420, 0xC)
+        );
+        assert_eq!(found.referenced_by, None);
+    }
+
+    #[test]
+    fn zero_filled_located_by_reference() {
+        let obj = ObjBuilder::new("zeros")
+            .section(".data", SectionKind::Data, &[0; 8])
+            .symbol("zbuf", SymbolSection::Section(0), 4, 4)
+            .build();
+        let signature = Signature::new(obj, None, None).unwrap();
+        let regions = [region(&[0; 0x20])];
+        assert!(matches!(
+            search(&regions, &signature, 0, 4),
+            Located::NotFound
+        ));
+
+        let recovered = [
+            Symbol::new("zbuf", 0x80000404, 0, "zeros", false), // Its own reference does not count
+            Symbol::new("zbuf", 0x80000434, 0, "caller", false),
+        ];
+        let found = locate_by_reference(&regions, &signature, 0, &[], &recovered).unwrap();
+        assert_eq!((found.start, found.vram), (0x1030, 0x80000430));
+        assert_eq!(found.referenced_by.as_deref(), Some("caller"));
+        // Nor can it be anywhere its contents did not match
+        assert_eq!(
+            locate_by_reference(&regions, &signature, 0, &[0x1000], &recovered),
+            None
+        );
+    }
+
+    #[test]
+    fn pointers_from_function_table() {
+        // A pointer to another file's function, one back into the table itself, and plain data
+        let obj = ObjBuilder::new("table")
+            .section(".data", SectionKind::Data, &bytes(&[0, 8, 0xAAAAAAAA]))
+            .section_symbol(0)
+            .symbol("func_a", SymbolSection::Undefined, 0, 0)
+            .symbol("table_end", SymbolSection::Section(0), 8, 4)
+            .local()
+            .reloc(".data", 0, RelocationKind::Word32, "func_a", 0)
+            .reloc(".data", 4, RelocationKind::Word32, ".data", 0)
+            .build();
+        let signature = Signature::new(obj, None, None).unwrap();
+
+        let regions = [region(&[0x80001000, 0x80000408, 0xAAAAAAAA])];
+        assert_eq!(
+            parse_pointers(&regions, &signature, 0, 0x1000),
+            Some(vec![
+                Symbol::new("func_a", 0x80001000, 0, "table", false),
+                Symbol::new("table_end", 0x80000408, 4, "table", true),
+            ])
+        );
+        let regions = [region(&[0x80001000, 0x80000408, 0xBBBBBBBB])];
+        assert_eq!(parse_pointers(&regions, &signature, 0, 0x1000), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Module for diagnostics, i.e. messages about how the run went rather than its results. These all go to stderr so
+//! that stdout only ever carries the report.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Debug => "debug",
+        })
+    }
+}
+
+/// What a diagnostic is about, so that they can be grepped for or filtered by something other than a human.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    UnknownRomHeader,
+    SkippedObject,
+    NoText,
+    EmptyText,
+    ZeroText,
+    AmbiguousSymbols,
+    UnpairedLo16,
+    SignaturesWritten,
+    VersionDetected,
+    AmbiguousVersion,
+    VersionDeviation,
+    ProfileLoaded,
+    LikelyPresent,
+    AnchoredSearch,
+    OverlappingMatch,
+    PlacementRejected,
+    LinkOrder,
+    DataLocated,
+    BssInconsistent,
+    GpInferred,
+    CommonInconsistent,
+    DataMismatch,
+    SizeConflict,
+    SymbolConflict,
+    UnreadableBinary,
+    InvalidProfile,
+    BadSignatureFile,
+    NoObjects,
+    UnwritableOutput,
+}
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::UnknownRomHeader => "unknown-rom-header",
+            Code::SkippedObject => "skipped-object",
+            Code::NoText => "no-text",
+            Code::EmptyText => "empty-text",
+            Code::ZeroText => "zero-text",
+            Code::AmbiguousSymbols => "ambiguous-symbols",
+            Code::UnpairedLo16 => "unpaired-lo16",
+            Code::SignaturesWritten => "signatures-written",
+            Code::VersionDetected => "version-detected",
+            Code::AmbiguousVersion => "ambiguous-version",
+            Code::VersionDeviation => "version-deviation",
+            Code::ProfileLoaded => "profile-loaded",
+            Code::LikelyPresent => "likely-present",
+            Code::AnchoredSearch => "anchored-search",
+            Code::OverlappingMatch => "overlapping-match",
+            Code::PlacementRejected => "placement-rejected",
+            Code::LinkOrder => "link-order",
+            Code::DataLocated => "data-located",
+            Code::BssInconsistent => "bss-inconsistent",
+            Code::GpInferred => "gp-inferred",
+            Code::CommonInconsistent => "common-inconsistent",
+            Code::DataMismatch => "data-mismatch",
+            Code::SizeConflict => "size-conflict",
+            Code::SymbolConflict => "symbol-conflict",
+            Code::UnreadableBinary => "unreadable-binary",
+            Code::InvalidProfile => "invalid-profile",
+            Code::BadSignatureFile => "bad-signature-file",
+            Code::NoObjects => "no-objects",
+            Code::UnwritableOutput => "unwritable-output",
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Severity::Warning as u8);
+
+/// Set the least severe diagnostic that will be shown.
+pub fn set_level(level: Severity) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Work out the level from the number of `-v`s and whether `-q` was given.
+pub fn level_from_args(verbose: u8, quiet: bool) -> Severity {
+    match (quiet, verbose) {
+        (true, _) => Severity::Error,
+        (false, 0) => Severity::Warning,
+        (false, 1) => Severity::Info,
+        (false, _) => Severity::Debug,
+    }
+}
+
+/// Show a diagnostic on stderr if it is severe enough. `object` is the object file it concerns, if any.
+pub fn emit(severity: Severity, code: Code, object: Option<&str>, message: fmt::Arguments) {
+    if severity as u8 > LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+    match object {
+        Some(object) => eprintln!("{}[{}] {}: {}", severity, code.as_str(), object, message),
+        None => eprintln!("{}[{}] {}", severity, code.as_str(), message),
+    }
+}
+
+pub fn error(code: Code, object: Option<&str>, message: fmt::Arguments) {
+    emit(Severity::Error, code, object, message);
+}
+
+pub fn warning(code: Code, object: Option<&str>, message: fmt::Arguments) {
+    emit(Severity::Warning, code, object, message);
+}
+
+pub fn info(code: Code, object: Option<&str>, message: fmt::Arguments) {
+    emit(Severity::Info, code, object, message);
+}
+
+pub fn debug(code: Code, object: Option<&str>, message: fmt::Arguments) {
+    emit(Severity::Debug, code, object, message);
+}
@@ -0,0 +1,474 @@
+//! Module for reading MIPS ECOFF relocatable objects, as produced by IDO and other SGI toolchains. Only the parts of
+//! the symbolic information needed to name symbols are read: local symbols, external symbols and their strings.
+
+use crate::binary::Endian;
+use crate::objfile::{
+    ObjFile, ObjSymbol, ObjectError, Relocation, RelocationKind, Section, SectionKind, SymbolKind,
+    SymbolSection,
+};
+
+const BIG_MAGICS: &[u16] = &[0x0160, 0x0163, 0x0140];
+const LITTLE_MAGICS: &[u16] = &[0x0162, 0x0166, 0x0142];
+
+const FILE_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const RELOCATION_SIZE: usize = 8;
+const SYMBOLIC_HEADER_SIZE: usize = 96;
+const FILE_DESCRIPTOR_SIZE: usize = 72;
+const LOCAL_SYMBOL_SIZE: usize = 12;
+const EXTERNAL_SYMBOL_SIZE: usize = 16;
+
+// Section flags
+const STYP_TEXT: u32 = 0x20;
+const STYP_DATA: u32 = 0x40;
+const STYP_BSS: u32 = 0x80;
+const STYP_RDATA: u32 = 0x100;
+const STYP_SDATA: u32 = 0x200;
+const STYP_SBSS: u32 = 0x400;
+const STYP_LIT8: u32 = 0x0800_0000;
+const STYP_LIT4: u32 = 0x1000_0000;
+
+// Relocation types
+const R_REFWORD: u32 = 2;
+const R_JMPADDR: u32 = 3;
+const R_REFHI: u32 = 4;
+const R_REFLO: u32 = 5;
+const R_GPREL: u32 = 6;
+const R_LITERAL: u32 = 7;
+
+/// Sections that local relocations can refer to, by their number.
+const RELOCATION_SECTIONS: &[&str] = &[
+    "", ".text", ".rdata", ".data", ".sdata", ".sbss", ".bss", ".init", ".lit8", ".lit4",
+];
+
+// Symbol types
+const ST_STATIC: u32 = 2;
+const ST_PROC: u32 = 6;
+const ST_STATIC_PROC: u32 = 14;
+
+// Storage classes
+const SC_TEXT: u32 = 1;
+const SC_DATA: u32 = 2;
+const SC_BSS: u32 = 3;
+const SC_ABS: u32 = 5;
+const SC_UNDEFINED: u32 = 6;
+const SC_SDATA: u32 = 13;
+const SC_SBSS: u32 = 14;
+const SC_RDATA: u32 = 15;
+const SC_COMMON: u32 = 17;
+const SC_SCOMMON: u32 = 18;
+const SC_SUNDEFINED: u32 = 21;
+const SC_INIT: u32 = 22;
+
+pub fn endian(data: &[u8]) -> Option<Endian> {
+    let bytes = [*data.first()?, *data.get(1)?];
+    if BIG_MAGICS.contains(&u16::from_be_bytes(bytes)) {
+        Some(Endian::Big)
+    } else if LITTLE_MAGICS.contains(&u16::from_le_bytes(bytes)) {
+        Some(Endian::Little)
+    } else {
+        None
+    }
+}
+
+struct Reader<'data> {
+    data: &'data [u8],
+    endian: Endian,
+}
+
+impl<'data> Reader<'data> {
+    fn bytes(&self, offset: usize, count: usize) -> Result<&'data [u8], ObjectError> {
+        Ok(self
+            .data
+            .get(offset..offset + count)
+            .ok_or("unexpected end of ECOFF object")?)
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, ObjectError> {
+        let bytes = self.bytes(offset, 2)?.try_into().unwrap();
+        Ok(match self.endian {
+            Endian::Big => u16::from_be_bytes(bytes),
+            Endian::Little => u16::from_le_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, ObjectError> {
+        let bytes = self.bytes(offset, 4)?.try_into().unwrap();
+        Ok(match self.endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn string(&self, offset: usize) -> Result<String, ObjectError> {
+        let bytes = self.data.get(offset..).ok_or("bad ECOFF string offset")?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).to_string())
+    }
+
+    /// Unpack the type and storage class of a symbol, which are bitfields laid out according to the endianness.
+    fn symbol_bits(&self, offset: usize) -> Result<(u32, u32), ObjectError> {
+        let bits = self.u32(offset)?;
+        Ok(match self.endian {
+            Endian::Big => (bits >> 26, (bits >> 21) & 0x1F),
+            Endian::Little => (bits & 0x3F, (bits >> 6) & 0x1F),
+        })
+    }
+}
+
+struct SectionHeader {
+    vaddr: u32,
+    relocations_offset: usize,
+    relocation_count: usize,
+}
+
+fn section_kind(flags: u32) -> SectionKind {
+    match flags {
+        STYP_TEXT => SectionKind::Text,
+        STYP_DATA | STYP_SDATA => SectionKind::Data,
+        STYP_RDATA | STYP_LIT8 | STYP_LIT4 => SectionKind::ReadOnlyData,
+        STYP_BSS | STYP_SBSS => SectionKind::Bss,
+        _ => SectionKind::Other,
+    }
+}
+
+fn storage_class_section(storage_class: u32) -> Option<&'static str> {
+    match storage_class {
+        SC_TEXT => Some(".text"),
+        SC_DATA => Some(".data"),
+        SC_BSS => Some(".bss"),
+        SC_SDATA => Some(".sdata"),
+        SC_SBSS => Some(".sbss"),
+        SC_RDATA => Some(".rdata"),
+        SC_INIT => Some(".init"),
+        _ => None,
+    }
+}
+
+/// Read an ECOFF relocatable object.
+pub fn parse(name: &str, data: &[u8]) -> Result<ObjFile, ObjectError> {
+    let endian = endian(data).ok_or("not an ECOFF object")?;
+    let reader = Reader { data, endian };
+
+    let section_count = reader.u16(2)? as usize;
+    let symbolic_header_offset = reader.u32(8)? as usize;
+    let optional_header_size = reader.u16(16)? as usize;
+    if symbolic_header_offset == 0 {
+        return Err(ObjectError::NoSymtab);
+    }
+
+    let mut sections = Vec::new();
+    let mut headers = Vec::new();
+    let mut symbols = Vec::new();
+    for i in 0..section_count {
+        let offset = FILE_HEADER_SIZE + optional_header_size + i * SECTION_HEADER_SIZE;
+        let name = reader.string(offset)?;
+        let name = name.get(..8).unwrap_or(&name).to_string();
+        let size = reader.u32(offset + 16)?;
+        let data_offset = reader.u32(offset + 20)? as usize;
+        let kind = section_kind(reader.u32(offset + 36)?);
+
+        let section_data = if kind == SectionKind::Bss || data_offset == 0 {
+            Vec::new()
+        } else {
+            reader.bytes(data_offset, size as usize)?.to_vec()
+        };
+        headers.push(SectionHeader {
+            vaddr: reader.u32(offset + 12)?,
+            relocations_offset: reader.u32(offset + 24)? as usize,
+            relocation_count: reader.u16(offset + 32)? as usize,
+        });
+        // Local relocations refer to sections, so give each a symbol to point at
+        symbols.push(ObjSymbol {
+            name: name.clone(),
+            kind: SymbolKind::Section,
+            section: SymbolSection::Section(i),
+            address: 0,
+            size: 0,
+            global: false,
+        });
+        sections.push(Section {
+            name,
+            kind,
+            size,
+            data: section_data,
+            relocations: Vec::new(),
+        });
+    }
+
+    let section_names: Vec<String> = sections
+        .iter()
+        .map(|section| section.name.clone())
+        .collect();
+    let section_by_name = |name: &str| section_names.iter().position(|n| n == name);
+
+    let external_base = symbols.len();
+    let header = |field: usize| reader.u32(symbolic_header_offset + 4 + 4 * field);
+    reader.bytes(symbolic_header_offset, SYMBOLIC_HEADER_SIZE)?;
+    let local_symbols_offset = header(8)? as usize;
+    let local_strings_offset = header(14)? as usize;
+    let external_strings_offset = header(16)? as usize;
+    let file_count = header(17)? as usize;
+    let files_offset = header(18)? as usize;
+    let external_count = header(21)? as usize;
+    let externals_offset = header(22)? as usize;
+
+    let symbol_section = |storage_class, value: u32| match storage_class {
+        SC_UNDEFINED | SC_SUNDEFINED => (SymbolSection::Undefined, 0),
+        SC_COMMON | SC_SCOMMON => (SymbolSection::Common, 0),
+        SC_ABS => (SymbolSection::Absolute, value),
+        _ => match storage_class_section(storage_class).and_then(section_by_name) {
+            Some(index) => (
+                SymbolSection::Section(index),
+                value.wrapping_sub(headers[index].vaddr),
+            ),
+            None => (SymbolSection::Absolute, value),
+        },
+    };
+
+    // Externals come first so that relocations can index them directly
+    for i in 0..external_count {
+        let offset = externals_offset + i * EXTERNAL_SYMBOL_SIZE + 4;
+        let name = reader.string(external_strings_offset + reader.u32(offset)? as usize)?;
+        let value = reader.u32(offset + 4)?;
+        let (symbol_type, storage_class) = reader.symbol_bits(offset + 8)?;
+        let (section, address) = symbol_section(storage_class, value);
+        symbols.push(ObjSymbol {
+            name,
+            kind: if symbol_type == ST_PROC || storage_class == SC_TEXT {
+                SymbolKind::Text
+            } else {
+                SymbolKind::Data
+            },
+            section,
+            address,
+            // Common symbols store their size as the value
+            size: if section == SymbolSection::Common {
+                value
+            } else {
+                0
+            },
+            global: true,
+        });
+    }
+
+    // Static functions and variables are only in the local symbols
+    for i in 0..file_count {
+        let offset = files_offset + i * FILE_DESCRIPTOR_SIZE;
+        let strings_base = reader.u32(offset + 8)? as usize;
+        let symbols_base = reader.u32(offset + 16)? as usize;
+        let symbol_count = reader.u32(offset + 20)? as usize;
+
+        for j in symbols_base..symbols_base + symbol_count {
+            let offset = local_symbols_offset + j * LOCAL_SYMBOL_SIZE;
+            let (symbol_type, storage_class) = reader.symbol_bits(offset + 8)?;
+            if symbol_type != ST_STATIC && symbol_type != ST_STATIC_PROC {
+                continue;
+            }
+            let string_offset = local_strings_offset + strings_base;
+            let name = reader.string(string_offset + reader.u32(offset)? as usize)?;
+            let (section, address) = symbol_section(storage_class, reader.u32(offset + 4)?);
+            symbols.push(ObjSymbol {
+                name,
+                kind: if symbol_type == ST_STATIC_PROC {
+                    SymbolKind::Text
+                } else {
+                    SymbolKind::Data
+                },
+                section,
+                address,
+                size: 0,
+                global: false,
+            });
+        }
+    }
+
+    for (index, header) in headers.iter().enumerate() {
+        let kind = sections[index].kind;
+        for i in 0..header.relocation_count {
+            let offset = header.relocations_offset + i * RELOCATION_SIZE;
+            let vaddr = reader.u32(offset)?;
+            let bits = reader.bytes(offset + 4, 4)?;
+            let (symbol_index, reloc_type, external) = match endian {
+                Endian::Big => (
+                    u32::from_be_bytes([0, bits[0], bits[1], bits[2]]) as usize,
+                    (bits[3] as u32 & 0x1E) >> 1,
+                    bits[3] & 0x01 != 0,
+                ),
+                Endian::Little => (
+                    u32::from_le_bytes([bits[0], bits[1], bits[2], 0]) as usize,
+                    (bits[3] as u32 & 0x78) >> 3,
+                    bits[3] & 0x80 != 0,
+                ),
+            };
+
+            let reloc_kind = match reloc_type {
+                R_REFWORD => RelocationKind::Word32,
+                R_JMPADDR => RelocationKind::Mips26,
+                R_REFHI => RelocationKind::Hi16,
+                R_REFLO => RelocationKind::Lo16,
+                R_GPREL | R_LITERAL => RelocationKind::Gprel16,
+                other if kind == SectionKind::Text => {
+                    return Err(ObjectError::UnsupportedRelocation(format!(
+                        "ECOFF type {} in .text",
+                        other
+                    )))
+                }
+                _ => continue,
+            };
+
+            // The implicit addend of a local relocation includes the address of the section in the object, so take
+            // it off again
+            let (symbol, addend) = if external {
+                (external_base + symbol_index, 0)
+            } else {
+                let section_name = RELOCATION_SECTIONS
+                    .get(symbol_index)
+                    .ok_or("bad ECOFF relocation section")?;
+                let target =
+                    section_by_name(section_name).ok_or("relocation against missing section")?;
+                (target, -(headers[target].vaddr as i64))
+            };
+            if symbol >= symbols.len() {
+                return Err("bad ECOFF relocation symbol".into());
+            }
+
+            sections[index].relocations.push(Relocation {
+                offset: vaddr.wrapping_sub(header.vaddr),
+                kind: reloc_kind,
+                symbol,
+                addend,
+            });
+        }
+    }
+
+    Ok(ObjFile {
+        name: name.to_string(),
+        endian,
+        sections,
+        symbols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A big-endian object with a function calling `ext` and loading the address of `.data`, and a static function.
+    fn test_object() -> Vec<u8> {
+        let mut obj = Vec::new();
+        let word = |obj: &mut Vec<u8>, value: u32| obj.extend(value.to_be_bytes());
+
+        // File header
+        obj.extend([0x01, 0x60, 0, 2]);
+        word(&mut obj, 0);
+        word(&mut obj, 144);
+        word(&mut obj, 96);
+        obj.extend([0, 0, 0, 0]);
+        // Section headers
+        for (name, vaddr, size, data, relocs, count, flags) in [
+            (b".text\0\0\0", 0, 16, 100, 120, 3, STYP_TEXT),
+            (b".data\0\0\0", 16, 4, 116, 0, 0, STYP_DATA),
+        ] {
+            obj.extend(name);
+            for value in [vaddr, vaddr, size, data, relocs, 0] {
+                word(&mut obj, value);
+            }
+            obj.extend([0, count, 0, 0]);
+            word(&mut obj, flags);
+        }
+        // .text: jal ext; nop; lui a0, %hi(.data); addiu a0, a0, %lo(.data)
+        for value in [0x0C00_0000, 0, 0x3C04_0000, 0x2484_0010] {
+            word(&mut obj, value);
+        }
+        // .data
+        word(&mut obj, 1);
+        // Relocations
+        for (vaddr, index, reloc_type, external) in [
+            (0, 1, R_JMPADDR, 1),
+            (8, 3, R_REFHI, 0),
+            (12, 3, R_REFLO, 0),
+        ] {
+            word(&mut obj, vaddr);
+            obj.extend([0, 0, index, (reloc_type as u8) << 1 | external]);
+        }
+        // Symbolic header
+        obj.extend([0x70, 0x09, 0, 0]);
+        for field in 0..23 {
+            let value = match field {
+                7 => 1,
+                8 => 240,
+                13 => 7,
+                14 => 252,
+                15 => 9,
+                16 => 260,
+                17 => 1,
+                18 => 272,
+                21 => 2,
+                22 => 344,
+                _ => 0,
+            };
+            word(&mut obj, value);
+        }
+        // Local symbols and strings
+        word(&mut obj, 0);
+        word(&mut obj, 8);
+        word(&mut obj, ST_STATIC_PROC << 26 | SC_TEXT << 21);
+        obj.extend(b"helper\0\0");
+        obj.extend(b"func\0ext\0\0\0\0");
+        // File descriptor
+        for value in [0, 0, 0, 7, 0, 1] {
+            word(&mut obj, value);
+        }
+        obj.extend([0; 48]);
+        // External symbols
+        for (string, symbol_type, storage_class) in [(0, ST_PROC, SC_TEXT), (5, 1, SC_UNDEFINED)] {
+            word(&mut obj, 0);
+            word(&mut obj, string);
+            word(&mut obj, 0);
+            word(&mut obj, symbol_type << 26 | storage_class << 21);
+        }
+        obj
+    }
+
+    #[test]
+    fn object() {
+        let data = test_object();
+        assert_eq!(endian(&data), Some(Endian::Big));
+        let obj = parse("test", &data).unwrap();
+
+        let text = obj.section_by_name(".text").unwrap();
+        assert_eq!(text.kind, SectionKind::Text);
+        assert_eq!(&text.data[12..], &0x2484_0010u32.to_be_bytes());
+        assert_eq!(obj.section_by_name(".data").unwrap().data, [0, 0, 0, 1]);
+
+        let targets: Vec<_> = text
+            .relocations
+            .iter()
+            .map(|reloc| {
+                (
+                    reloc.offset,
+                    reloc.kind,
+                    obj.symbols[reloc.symbol].name.as_str(),
+                    reloc.addend,
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                (0, RelocationKind::Mips26, "ext", 0),
+                (8, RelocationKind::Hi16, ".data", -16),
+                (12, RelocationKind::Lo16, ".data", -16),
+            ]
+        );
+
+        let defined: Vec<_> = obj
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Text && symbol.is_definition())
+            .map(|symbol| (symbol.name.as_str(), symbol.address, symbol.global))
+            .collect();
+        assert_eq!(defined, [("func", 0, true), ("helper", 8, false)]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Module for explaining how the search went for one object: whether the rough pass found anything, and if the precise
+//! pass rejected it, where and why.
+
+use crate::binary::Region;
+use crate::data;
+use crate::objfile::Relocation;
+use crate::placement::{self, Constraints, Rejection};
+use crate::sigs::Signature;
+use crate::{make_rough_stencil, naive_wordsearch, words_from_bytes, PreciseStencil, TAB};
+use std::ops::Range;
+
+/// How many of the best candidates to show.
+const CANDIDATES_SHOWN: usize = 5;
+
+/// How many words of each position to compare when the rough pass found nothing.
+const FALLBACK_WORDS: usize = 16;
+
+/// One place the object might have been.
+struct Candidate {
+    offset: usize, // In the binary
+    vram: u32,
+    rough: bool,                   // Passed the rough pass
+    matching: usize,               // Words that pass the precise check
+    first_mismatch: Option<usize>, // Index of the first word that does not
+    rejection: Option<Rejection>,  // Why it would be ruled out even if it matched
+}
+
+fn check(stencil: &[PreciseStencil], words: &[u32]) -> (usize, Option<usize>) {
+    let mut matching = 0;
+    let mut first_mismatch = None;
+    for (i, (instr, word)) in stencil.iter().zip(words).enumerate() {
+        if word & instr.mask == instr.word {
+            matching += 1;
+        } else if first_mismatch.is_none() {
+            first_mismatch = Some(i);
+        }
+    }
+    (matching, first_mismatch)
+}
+
+/// An addend as it would be written after a symbol, e.g. ` - 0x8`, or nothing if it is zero.
+fn format_addend(addend: i64) -> String {
+    match addend {
+        0 => String::new(),
+        1.. => format!(" + {:#X}", addend),
+        _ => format!(" - {:#X}", addend.unsigned_abs()),
+    }
+}
+
+/// Print the explanation for one object to stdout. Candidates are also checked against where the search would accept
+/// a match: the constraints, the file's `alignment`, and the link order `bounds`.
+pub fn explain(
+    regions: &[Region],
+    signature: &Signature,
+    constraints: &Constraints,
+    alignment: u32,
+    bounds: &Range<usize>,
+) {
+    let obj = &signature.obj;
+    let stencil = &signature.stencil;
+    match &signature.version {
+        Some(version) => println!("{} ({}):", obj.name, version),
+        None => println!("{}:", obj.name),
+    }
+
+    if let Some(index) = data::data_section(obj) {
+        println!(
+            "{}no code, so it is searched for by its {} (or where references put it) instead",
+            TAB, obj.sections[index].name
+        );
+        return;
+    }
+    let Some(text) = obj.section_by_name(".text") else {
+        println!("{}no .text section, so it is never searched for", TAB);
+        return;
+    };
+    let mut words = Vec::new();
+    words_from_bytes(&text.data, obj.endian, &mut words);
+    if words.is_empty() || words.iter().all(|word| *word == 0) {
+        println!(
+            "{}.text is empty or all zeros, so it is never searched for",
+            TAB
+        );
+        return;
+    }
+    println!(
+        "{}.text is {:#X} bytes ({} words) with {} relocations",
+        TAB,
+        text.size,
+        stencil.len(),
+        text.relocations.len()
+    );
+
+    let mut rough_stencil = Vec::new();
+    make_rough_stencil(&text.data, obj.endian, &mut rough_stencil);
+
+    let searched = regions
+        .iter()
+        .filter(|region| region.endian == obj.endian && region.words.len() >= stencil.len())
+        .map(|region| (region, naive_wordsearch(&region.words, &rough_stencil)))
+        .collect::<Vec<_>>();
+    let rough_hits = searched
+        .iter()
+        .map(|(_, results)| results.len())
+        .sum::<usize>();
+    println!("{}rough pass hits: {}", TAB, rough_hits);
+
+    // With no rough hits every position is a candidate, so only their first words are compared to keep this linear in
+    // the size of the binary. The best are checked in full below.
+    let ranked = if rough_hits == 0 {
+        &stencil[..stencil.len().min(FALLBACK_WORDS)]
+    } else {
+        &stencil[..]
+    };
+    let mut candidates = Vec::new();
+    for (region, rough_results) in &searched {
+        let positions = if rough_hits == 0 {
+            (0..=region.words.len() - stencil.len())
+                .map(|i| i * 4)
+                .collect()
+        } else {
+            rough_results.clone()
+        };
+        for position in positions {
+            let index = position / 4;
+            let (matching, first_mismatch) =
+                check(ranked, &region.words[index..index + ranked.len()]);
+            let offset = region.offset + position;
+            let vram = region.vram + position as u32;
+            let rejection = constraints
+                .check(offset, vram, text.size as usize, alignment)
+                .and_then(|()| placement::check_link_order(bounds, offset, text.size as usize))
+                .err();
+            candidates.push(Candidate {
+                offset,
+                vram,
+                rough: rough_results.contains(&position),
+                matching,
+                first_mismatch,
+                rejection,
+            });
+        }
+    }
+
+    if candidates.is_empty() {
+        println!("{}nowhere in the binary is large enough to hold it", TAB);
+        return;
+    }
+
+    // Most matching words first, then the furthest first mismatch
+    let rank = |candidate: &Candidate| {
+        (
+            std::cmp::Reverse(candidate.matching),
+            std::cmp::Reverse(candidate.first_mismatch.unwrap_or(usize::MAX)),
+            candidate.offset,
+        )
+    };
+    candidates.sort_by_key(rank);
+    candidates.truncate(CANDIDATES_SHOWN);
+    if ranked.len() < stencil.len() {
+        println!(
+            "{}every position was ranked by its first {} words only",
+            TAB,
+            ranked.len()
+        );
+        for candidate in &mut candidates {
+            let region = regions
+                .iter()
+                .find(|region| region.vram_of(candidate.offset).is_some())
+                .unwrap();
+            let index = (candidate.offset - region.offset) / 4;
+            (candidate.matching, candidate.first_mismatch) =
+                check(stencil, &region.words[index..index + stencil.len()]);
+        }
+        candidates.sort_by_key(rank);
+    }
+
+    println!("{}best candidates:", TAB);
+    for candidate in &candidates {
+        print!(
+            "{}{:#X} (vram {:#X}): {} of {} words match",
+            TAB,
+            candidate.offset,
+            candidate.vram,
+            candidate.matching,
+            stencil.len()
+        );
+        if !candidate.rough {
+            print!(", failed the rough pass");
+        }
+        match candidate.first_mismatch {
+            Some(index) => println!(", first mismatch at word {} (+{:#X})", index, index * 4),
+            None => println!(", a match"),
+        }
+        if let Some(rejection) = candidate.rejection {
+            println!("{}{}placement ruled out: {}", TAB, TAB, rejection);
+        }
+        let Some(index) = candidate.first_mismatch else {
+            continue;
+        };
+
+        let region = regions
+            .iter()
+            .find(|region| region.vram_of(candidate.offset).is_some())
+            .unwrap();
+        let actual = region.words[(candidate.offset - region.offset) / 4 + index];
+        let instr = &stencil[index];
+        println!(
+            "{}{}expected {:#010X} under mask {:#010X}, found {:#010X} ({:#010X} masked)",
+            TAB,
+            TAB,
+            instr.word,
+            instr.mask,
+            actual,
+            actual & instr.mask
+        );
+
+        let covering = text
+            .relocations
+            .iter()
+            .filter(|reloc| reloc.offset as usize / 4 == index)
+            .collect::<Vec<&Relocation>>();
+        for reloc in covering {
+            println!(
+                "{}{}relocation: {:?} against {}{}",
+                TAB,
+                TAB,
+                reloc.kind,
+                obj.symbols[reloc.symbol].name,
+                format_addend(reloc.addend)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_mismatch() {
+        let stencil = [
+            PreciseStencil {
+                word: 0x0C000000,
+                addend: 0,
+                mask: 0xFC000000,
+            },
+            PreciseStencil {
+                word: 0x24020001,
+                addend: 0,
+                mask: 0xFFFFFFFF,
+            },
+        ];
+        assert_eq!(check(&stencil, &[0x0C012345, 0x24020001]), (2, None));
+        assert_eq!(check(&stencil, &[0x0C012345, 0x24020002]), (1, Some(1)));
+    }
+
+    #[test]
+    fn addends() {
+        assert_eq!(format_addend(0), "");
+        assert_eq!(format_addend(0x10), " + 0x10");
+        assert_eq!(format_addend(-8), " - 0x8");
+    }
+}
@@ -0,0 +1,226 @@
+//! Module for fitting the found files to the link order and predicting where the rest are. Between two found files
+//! that are in order, only the files linked between them can lie, and their sizes say where each should start. Not
+//! every member of an archive is necessarily pulled in, so the prediction is what to check, not a result.
+
+use crate::binary::Region;
+use crate::profile::Profiles;
+use crate::sigs::Signature;
+use crate::{first_max_by_key, FoundFile};
+
+/// Where a file that was not placed is expected to start.
+#[derive(Debug, PartialEq)]
+pub struct Predicted {
+    pub name: String,
+    pub start: usize,
+    pub size: usize,
+}
+
+/// The space between two found files that are in link order, and the files expected to lie in it.
+#[derive(Debug, PartialEq)]
+pub struct Gap {
+    pub after: String,
+    pub before: String,
+    pub start: usize,
+    pub end: usize,
+    pub expected: Vec<Predicted>,
+}
+
+impl Gap {
+    /// Whether every file expected here fits in the space.
+    pub fn fits(&self) -> bool {
+        self.expected
+            .last()
+            .is_none_or(|last| last.start + last.size <= self.end)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Layout {
+    pub out_of_order: Vec<String>, // Found files that do not fit the link order with the others
+    pub gaps: Vec<Gap>,
+}
+
+/// Indices of a longest strictly increasing subsequence of `values`.
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    // For each element, the length of the longest run ending there and the element before it in that run
+    let mut lengths = vec![1; values.len()];
+    let mut previous = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut indices = Vec::new();
+    let mut current = first_max_by_key(0..values.len(), |&i| lengths[i]);
+    while let Some(i) = current {
+        indices.push(i);
+        current = previous[i];
+    }
+    indices.reverse();
+    indices
+}
+
+/// Fit the found files to each link order and predict where the files between them that were not placed should be.
+/// `unplaced` are the files that were searched for but not found.
+pub fn infer(
+    orders: &[Vec<String>],
+    found: &[FoundFile],
+    unplaced: &[&Signature],
+    regions: &[Region],
+    profiles: &Profiles,
+) -> Layout {
+    let mut layout = Layout::default();
+
+    for order in orders {
+        let in_order = order
+            .iter()
+            .filter_map(|name| found.iter().find(|file| &file.name == name))
+            .collect::<Vec<_>>();
+        let starts = in_order
+            .iter()
+            .map(|file| file.text_start)
+            .collect::<Vec<_>>();
+        let fitted = longest_increasing(&starts);
+        for (index, file) in in_order.iter().enumerate() {
+            if !fitted.contains(&index) && !layout.out_of_order.contains(&file.name) {
+                layout.out_of_order.push(file.name.clone());
+            }
+        }
+
+        for pair in fitted.windows(2) {
+            let (after, before) = (in_order[pair[0]], in_order[pair[1]]);
+            let first = order.iter().position(|name| name == &after.name).unwrap();
+            let last = order.iter().position(|name| name == &before.name).unwrap();
+
+            let start = after.text_start + after.text_size;
+            let Some(vram) = regions
+                .iter()
+                .find_map(|region| region.vram_of(after.text_start))
+            else {
+                continue;
+            };
+            // Alignment applies to addresses, so follow along in vram
+            let mut cursor = vram + after.text_size as u32;
+            let mut expected = Vec::new();
+            for name in &order[first + 1..last] {
+                let Some(signature) = unplaced
+                    .iter()
+                    .find(|signature| &signature.obj.name == name)
+                else {
+                    continue;
+                };
+                let alignment = profiles.alignment(name);
+                cursor = cursor.next_multiple_of(alignment);
+                let size = signature.obj.section_by_name(".text").unwrap().size as usize;
+                expected.push(Predicted {
+                    name: name.clone(),
+                    start: after.text_start + (cursor - vram) as usize,
+                    size,
+                });
+                cursor += size as u32;
+            }
+
+            if !expected.is_empty() {
+                layout.gaps.push(Gap {
+                    after: after.name.clone(),
+                    before: before.name.clone(),
+                    start,
+                    end: before.text_start,
+                    expected,
+                });
+            }
+        }
+    }
+
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::Endian;
+    use crate::confidence::Confidence;
+    use crate::objfile::ObjBuilder;
+    use crate::profile::Profile;
+
+    #[test]
+    fn out_of_order_files() {
+        assert_eq!(longest_increasing(&[0x100, 0x900, 0x200, 0x300]), [0, 2, 3]);
+        assert_eq!(longest_increasing(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn gaps_between_found_files() {
+        let found = |name: &str, text_start, text_size| FoundFile {
+            name: name.to_string(),
+            text_start,
+            text_size,
+            anchored: false,
+            confidence: Confidence::default(),
+        };
+        let found = [
+            found("a", 0x1000, 0x24),
+            found("b", 0x1100, 0x10),
+            found("c", 0x1080, 0x8), // Linked after b, but found before it
+            found("d", 0x1200, 0x4),
+        ];
+        let unplaced = [("x", 6), ("y", 3), ("z", 2)].map(|(name, words)| {
+            Signature::new(
+                ObjBuilder::new(name).text(&vec![1; words]).build(),
+                None,
+                None,
+            )
+            .unwrap()
+        });
+        let region = Region {
+            offset: 0x1000,
+            vram: 0x80000400,
+            endian: Endian::Big,
+            words: vec![0; 0x100],
+        };
+        // z is not in the profile, so it keeps the default alignment
+        let profile: Profile =
+            toml::from_str("library = \"t\"\nalignment = 16\n[files]\nx = {}\ny = {}").unwrap();
+        let order = ["a", "x", "b", "y", "z", "c", "d"]
+            .map(String::from)
+            .to_vec();
+
+        let layout = infer(
+            &[order],
+            &found,
+            &unplaced.iter().collect::<Vec<_>>(),
+            &[region],
+            &Profiles(vec![profile]),
+        );
+        assert_eq!(layout.out_of_order, ["c"]);
+        let predicted = |name: &str, start, size| Predicted {
+            name: name.to_string(),
+            start,
+            size,
+        };
+        assert_eq!(
+            layout.gaps,
+            [
+                Gap {
+                    after: "a".to_string(),
+                    before: "b".to_string(),
+                    start: 0x1024,
+                    end: 0x1100,
+                    expected: vec![predicted("x", 0x1030, 0x18)],
+                },
+                Gap {
+                    after: "b".to_string(),
+                    before: "d".to_string(),
+                    start: 0x1110,
+                    end: 0x1200,
+                    expected: vec![predicted("y", 0x1110, 0xC), predicted("z", 0x111C, 0x8)],
+                },
+            ]
+        );
+        assert!(layout.gaps.iter().all(Gap::fits));
+    }
+}
@@ -1,516 +0,0 @@
-// Module for dealing specifically with libultra idiosyncracies
-
-/// Files known to be handwritten. These are taken from libgultra 2.0L, they may differ on other versions.
-pub const HANDWRITTEN_FILES: &'static [&str] = &[
-    // gu
-    "sqrtf",
-    "libm_vals",
-    // libc
-    "bzero",
-    "bcmp",
-    "bcopy",
-    // os
-    "setcount",
-    "setconfig",
-    "unmaptlb",
-    "writebackdcache",
-    "maptlbrdb",
-    "setwatchlo",
-    "setcause",
-    "writebackdcacheall",
-    "parameters",
-    "invaldcache",
-    "setfpccsr",
-    "getfpccsr",
-    "gettlbhi",
-    "setsr",
-    "gettlbpagemask",
-    "gettlbasid",
-    "setintmask",
-    "exceptasm",
-    "getconfig",
-    "getintmask",
-    "unmaptlball",
-    "settlbasid",
-    "gettlblo0",
-    "getcount",
-    "probetlb",
-    "getcompare",
-    "getsr",
-    "getcause",
-    "gettlblo1",
-    "invalicache",
-    "interrupt",
-    "setcompare",
-    "maptlb",
-    "getwatchlo",
-    // rmon
-    "rmonrcp",
-];
-
-/// Files that are too generic to necessarily believe their specific inclusion.
-pub const GENERIC_FILES: &'static [&str] = &["copy"];
-
-pub static AMBIGUOUS_FILES: &[&[&str]] = &[
-    &["cspdelete", "seqpdelete"],
-    &["cspgetchlvol", "seqpgetchlvol"],
-    &["cspgetfxmix", "seqpgetfxmix"],
-    &["cspgetpan", "seqpgetpan"],
-    &["cspgetpriority", "seqpgetpriority"],
-    &["cspgetprogram", "seqpgetprogram"],
-    &["cspgetseq", "seqpgetseq"],
-    &["cspgetstate", "seqpgetstate"],
-    &["cspgetvol", "seqpgetvol"],
-    &["cspplay", "seqpplay"],
-    &["cspsendmidi", "seqpsendmidi"],
-    &["cspsetbank", "seqpsetbank"],
-    &["cspsetchlvol", "seqpsetchlvol"],
-    &["cspsetfxmix", "seqpsetfxmix"],
-    &["cspsetpan", "seqpsetpan"],
-    &["cspsetpriority", "seqpsetpriority"],
-    &["cspsetprogram", "seqpsetprogram"],
-    &["cspsetseq", "seqpsetseq"],
-    &["cspsettempo", "seqpsettempo"],
-    &["cspsetvol", "seqpsetvol"],
-    &["cspstop", "seqpstop"],
-    &["epiread", "epiwrite"], // __osEPiRawReadIo,__osEPiRawWriteIo
-    &["getactivequeue", "getcurrfaultthread", "pigettype", "vigetcurrcontext", "vigetnextcontext"], 
-    // createthread,exceptasm,vimgr, pigettype search for 0x80000304
-    &["hide", "show"], // spSetAttribute,spClearAttribute
-    &["piacs", "siacs"], // __osPiCreateAccessQueue,__osSiCreateAccessQueue
-    &["piread", "piwrite"], // __osPiRawWriteIo,__osPiRawReadIo
-    &["sirawread", "sprawread"], // __osSiDeviceBusy,__osSpDeviceBusy
-    &["sirawwrite", "sprawwrite"], // __osSiDeviceBusy,__osSpDeviceBusy
-    &["vigetcurrframebuf", "vigetnextframebuf"], // __osViCurr,__osViNext
-];
-
-pub const PIGETTYPE_TEXT: &[u32] = &[0x3C028000u32, 0x03E00008u32, 0x8C420304u32];
-
-pub const FLAT_AMBIGUOUS_FILES: &[&str] = &[
-    "cspdelete",
-    "seqpdelete",
-    "cspgetchlvol",
-    "seqpgetchlvol",
-    "cspgetfxmix",
-    "seqpgetfxmix",
-    "cspgetpan",
-    "seqpgetpan",
-    "cspgetpriority",
-    "seqpgetpriority",
-    "cspgetprogram",
-    "seqpgetprogram",
-    "cspgetseq",
-    "seqpgetseq",
-    "cspgetstate",
-    "seqpgetstate",
-    "cspgetvol",
-    "seqpgetvol",
-    "cspplay",
-    "seqpplay",
-    "cspsendmidi",
-    "seqpsendmidi",
-    "cspsetbank",
-    "seqpsetbank",
-    "cspsetchlvol",
-    "seqpsetchlvol",
-    "cspsetfxmix",
-    "seqpsetfxmix",
-    "cspsetpan",
-    "seqpsetpan",
-    "cspsetpriority",
-    "seqpsetpriority",
-    "cspsetprogram",
-    "seqpsetprogram",
-    "cspsetseq",
-    "seqpsetseq",
-    "cspsettempo",
-    "seqpsettempo",
-    "cspsetvol",
-    "seqpsetvol",
-    "cspstop",
-    "seqpstop",
-    "epiread",
-    "epiwrite",
-    "getactivequeue",
-    "getcurrfaultthread",
-    "pigettype",
-    "vigetcurrcontext",
-    "vigetnextcontext",
-    "hide",
-    "show",
-    "piacs",
-    "siacs",
-    "piread",
-    "piwrite",
-    "sirawread",
-    "sprawread",
-    "sirawwrite",
-    "sprawwrite",
-    "vigetcurrframebuf",
-    "vigetnextframebuf",
-];
-
-// pub static FLATTENED: &[&str] = &AMBIGUOUS_FILES.into_iter().flatten().collect::<Vec<&str>>();
-
-pub const HANDWRITTEN_GU_FILES: &'static [&str] = &[
-    "scalef",
-    "mtxf2l",
-    "translate",
-    "mtxl2f",
-    "mtxident",
-    "translatef",
-    "scale",
-    "normalize",
-    "mtxidentf",
-    "mtxcatf",
-];
-
-pub const FILES: &'static [&str] = &[
-    // "bzero",
-    // "bcmp",
-    // "bcopy",
-    // "interrupt",
-    // "startthread",
-    // "createthread",
-    // "sinf",
-    // "cosf",
-    // "coss",
-    // "sins",
-    // "vigetcurrframebuf",
-    // audio
-    "auxbus",
-    "bnkf",
-    "cents2ratio",
-    "copy",
-    "cseq",
-    "cspdelete",
-    "cspgetchlvol",
-    "cspgetfxmix",
-    "cspgetpan",
-    "cspgetpriority",
-    "cspgetprogram",
-    "cspgetseq",
-    "cspgetstate",
-    "cspgettempo",
-    "cspgetvol",
-    "csplayer",
-    "cspplay",
-    "cspsendmidi",
-    "cspsetbank",
-    "cspsetchlvol",
-    "cspsetfxmix",
-    "cspsetpan",
-    "cspsetpriority",
-    "cspsetprogram",
-    "cspsetseq",
-    "cspsettempo",
-    "cspsetvol",
-    "cspstop",
-    "drvrNew",
-    "env",
-    "event",
-    "filter",
-    "heapalloc",
-    "heapcheck",
-    "heapinit",
-    "load",
-    "mainbus",
-    "parse_abi",
-    "resample",
-    "reverb",
-    "save",
-    "seq",
-    "seqpdelete",
-    "seqpgetchlvol",
-    "seqpgetfxmix",
-    "seqpgetpan",
-    "seqpgetpriority",
-    "seqpgetprogram",
-    "seqpgetseq",
-    "seqpgetstate",
-    "seqpgettempo",
-    "seqpgetvol",
-    "seqplayer",
-    "seqploop",
-    "seqpplay",
-    "seqpsendmidi",
-    "seqpsetbank",
-    "seqpsetchlvol",
-    "seqpsetfxmix",
-    "seqpsetpan",
-    "seqpsetpriority",
-    "seqpsetprogram",
-    "seqpsetseq",
-    "seqpsettempo",
-    "seqpsetvol",
-    "seqpstop",
-    "sl",
-    "sndpallocate",
-    "sndpdeallocate",
-    "sndpdelete",
-    "sndpgetsound",
-    "sndpgetstate",
-    "sndplayer",
-    "sndpplay",
-    "sndpplayat",
-    "sndpsetfxmix",
-    "sndpsetpan",
-    "sndpsetpitch",
-    "sndpsetpriority",
-    "sndpsetsound",
-    "sndpsetvol",
-    "sndpstop",
-    "synaddplayer",
-    "synallocfx",
-    "synallocvoice",
-    "syndelete",
-    "synfreefx",
-    "synfreevoice",
-    "syngetfxref",
-    "syngetpriority",
-    "synremoveplayer",
-    "synsetfxmix",
-    "synsetfxparam",
-    "synsetpan",
-    "synsetpitch",
-    "synsetpriority",
-    "synsetvol",
-    "synstartvoice",
-    "synstartvoiceparam",
-    "synstopvoice",
-    "synthesizer",
-    // gu
-    "gt",
-    "align",
-    "cosf",
-    "coss",
-    "frustum",
-    "guLoadTile_bug",
-    "libm_vals",
-    "loadtextureblockmipmap",
-    "lookat",
-    "lookathil",
-    "lookatref",
-    "lookatstereo",
-    "mtxcatf",
-    "mtxcatl",
-    "mtxutil",
-    "normalize",
-    "ortho",
-    "perspective",
-    "position",
-    "poslight",
-    "poslighthil",
-    "random",
-    "rotate",
-    "rotateRPY",
-    "scale",
-    "sinf",
-    "sins",
-    "sqrtf",
-    "translate",
-    "us2dex",
-    "us2dex_emu",
-    "usprite",
-    // io
-    "ai",
-    "aigetlen",
-    "aigetstat",
-    "aisetfreq",
-    "aisetnextbuf",
-    "cartrominit",
-    "conteeplongread",
-    "conteeplongwrite",
-    "conteepprobe",
-    "conteepread",
-    "conteepwrite",
-    "contpfs",
-    "contquery",
-    "contramread",
-    "contramwrite",
-    "contreaddata",
-    "contreset",
-    "controller",
-    "contsetch",
-    "crc",
-    "devmgr",
-    "dp",
-    "dpctr",
-    "dpgetstat",
-    "dpsetnextbuf",
-    "dpsetstat",
-    "driverominit",
-    "epidma",
-    "epigettype",
-    "epilinkhandle",
-    "epirawdma",
-    "epirawread",
-    "epirawwrite",
-    "epiread",
-    "epiwrite",
-    "leodiskinit",
-    "leointerrupt",
-    "motor",
-    "pfsallocatefile",
-    "pfschecker",
-    "pfsdeletefile",
-    "pfsfilestate",
-    "pfsfreeblocks",
-    "pfsgetlabel",
-    "pfsgetstatus",
-    "pfsinit",
-    "pfsinitpak",
-    "pfsisplug",
-    "pfsnumfiles",
-    "pfsreadwritefile",
-    "pfsreformat",
-    "pfsrepairid",
-    "pfssearchfile",
-    "pfssetlabel",
-    "pi",
-    "piacs",
-    "pidma",
-    "pigetcmdq",
-    "pigetstat",
-    "pigettype",
-    "pimgr",
-    "pirawdma",
-    "pirawread",
-    "pirawwrite",
-    "piread",
-    "piwrite",
-    "si",
-    "siacs",
-    "sigetstat",
-    "sirawdma",
-    "sirawread",
-    "sirawwrite",
-    "sp",
-    "spgetstat",
-    "sprawdma",
-    "sprawread",
-    "sprawwrite",
-    "spsetpc",
-    "spsetstat",
-    "sptask",
-    "sptaskyield",
-    "sptaskyielded",
-    "vi",
-    "viblack",
-    "vifade",
-    "vigetcurrcontext",
-    "vigetcurrframebuf",
-    "vigetfield",
-    "vigetline",
-    "vigetmode",
-    "vigetnextcontext",
-    "vigetnextframebuf",
-    "vigetstat",
-    "vimgr",
-    "virepeatline",
-    "visetevent",
-    "visetmode",
-    "visetspecial",
-    "visetxscale",
-    "visetyscale",
-    "viswapbuf",
-    "viswapcontext",
-    "vitbl",
-    // libc
-    "bcmp",
-    "bcopy",
-    "bzero",
-    "ldiv",
-    "ll",
-    "llbit",
-    "llcvt",
-    "sprintf",
-    "string",
-    "syncprintf",
-    "xldtob",
-    "xlitob",
-    "xprintf",
-    // os
-    "atomic",
-    "createmesgqueue",
-    "createthread",
-    "destroythread",
-    "exceptasm",
-    "getactivequeue",
-    "getcause",
-    "getcompare",
-    "getconfig",
-    "getcount",
-    "getcurrfaultthread",
-    "getfpccsr",
-    "getintmask",
-    "getmemsize",
-    "getnextfaultthread",
-    "getsr",
-    "getthreadid",
-    "getthreadpri",
-    "gettime",
-    "gettlbasid",
-    "gettlbhi",
-    "gettlblo0",
-    "gettlblo1",
-    "gettlbpagemask",
-    "initialize",
-    "initrdb",
-    "interrupt",
-    "invaldcache",
-    "invalicache",
-    "jammesg",
-    "kdebugserver",
-    "maptlb",
-    "maptlbrdb",
-    "parameters",
-    "physicaltovirtual",
-    "probetlb",
-    "rdbsend",
-    "recvmesg",
-    "resetglobalintmask",
-    "sendmesg",
-    "setcause",
-    "setcompare",
-    "setconfig",
-    "setcount",
-    "seteventmesg",
-    "setfpccsr",
-    "setglobalintmask",
-    "sethwinterrupt",
-    "setintmask",
-    "setsr",
-    "setthreadpri",
-    "settime",
-    "settimer",
-    "settlbasid",
-    "startthread",
-    "stopthread",
-    "stoptimer",
-    "syncputchars",
-    "thread",
-    "timerintr",
-    "unmaptlb",
-    "unmaptlball",
-    "virtualtophysical",
-    "writebackdcache",
-    "writebackdcacheall",
-    "yieldthread",
-    // rg
-    "free",
-    "getbufcount",
-    "getsize",
-    "malloc",
-    "region",
-    // sched
-    "sched",
-    // sp
-    "clearattribute",
-    "color",
-    "hide",
-    "setattribute",
-    "show",
-    "sprite",
-    "spscale",
-];
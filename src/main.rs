@@ -1,32 +1,87 @@
-use object::{Object, ObjectSection, RelocationKind};
+use anchor::Anchored;
+use binary::{BinaryKind, Endian, Region};
+use clap::{CommandFactory, Parser, Subcommand};
+use confidence::Confidence;
+use data::Located;
+use diag::Code;
+use objfile::{ObjFile, ObjectError, RelocationKind, SectionKind, SymbolSection};
+use overlap::Overlap;
+use placement::Constraints;
+use profile::{Profile, Profiles};
+use report::{Format, Report};
+use rom::ByteOrder;
+use sigs::Signature;
 use std::error::Error;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
-use symbols::Symbol;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use symbols::{GpReference, Symbol};
+use symtab::SymbolTable;
 
-mod libultra;
+mod anchor;
+mod binary;
+mod bss;
+mod common;
+mod confidence;
+mod coverage;
+mod data;
+mod diag;
+mod ecoff;
+mod explain;
+mod layout;
+mod objfile;
+mod overlap;
+mod placement;
+mod profile;
+mod psyq;
+mod report;
+mod rom;
+mod sigs;
+mod sizes;
 mod splat;
 mod symbols;
+mod symtab;
+mod version;
 
 const TAB: &str = "    ";
 
-// TODO: do this properly
-const BASE_ADDRESS: u32 = 0x80000400;
-
 const FULL_MASK: u32 = 0xFF_FF_FF_FF;
 const ROUGH_MASK: u32 = 0xFC_00_00_00;
 const J_TYPE_MASK: u32 = 0xFC_00_00_00;
 const I_TYPE_MASK: u32 = 0xFF_FF_00_00;
 
-fn words_from_be_bytes(input: &[u8], output: &mut Vec<u32>) -> () {
+fn words_from_bytes(input: &[u8], endian: Endian, output: &mut Vec<u32>) {
     for bytes in input.chunks_exact(4) {
-        output.push(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        output.push(match endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        });
+    }
+}
+
+/// The first of `items` with the greatest key, where `Iterator::max_by_key` gives the last. Ties are then settled by
+/// the order things were given or found in.
+pub fn first_max_by_key<T, K: Ord>(
+    items: impl IntoIterator<Item = T>,
+    mut key: impl FnMut(&T) -> K,
+) -> Option<T> {
+    let mut best: Option<(K, T)> = None;
+    for item in items {
+        let item_key = key(&item);
+        if best
+            .as_ref()
+            .is_none_or(|(best_key, _)| item_key > *best_key)
+        {
+            best = Some((item_key, item));
+        }
     }
+    best.map(|(_, item)| item)
 }
 
-fn make_rough_stencil(input: &[u8], output: &mut Vec<u32>) {
-    words_from_be_bytes(input, output);
+fn make_rough_stencil(input: &[u8], endian: Endian, output: &mut Vec<u32>) {
+    words_from_bytes(input, endian, output);
     for word in output {
         *word &= ROUGH_MASK;
     }
@@ -39,11 +94,15 @@ pub struct PreciseStencil {
     mask: u32,   // Mask applied
 }
 
-fn make_precise_stencil(obj_file: &object::File, input: &[u8]) -> Vec<PreciseStencil> {
+fn make_precise_stencil(
+    obj_file: &ObjFile,
+    input: &[u8],
+) -> Result<Vec<PreciseStencil>, ObjectError> {
     let mut output = Vec::new();
+    let mut words = Vec::new();
 
-    for bytes in input.chunks_exact(4) {
-        let word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    words_from_bytes(input, obj_file.endian, &mut words);
+    for word in words {
         output.push(PreciseStencil {
             word,
             addend: word,
@@ -52,31 +111,33 @@ fn make_precise_stencil(obj_file: &object::File, input: &[u8]) -> Vec<PreciseSte
     }
 
     if let Some(section) = obj_file.section_by_name(".text") {
-        for reloc in section.relocations() {
-            let index = (reloc.0 / 4) as usize;
-            match reloc.1.kind() {
-                RelocationKind::Elf(4) => {
-                    let mask = J_TYPE_MASK;
-                    output[index].word &= mask;
-                    output[index].addend &= !mask;
-                    output[index].mask &= mask;
-                }
-                RelocationKind::Elf(5) | RelocationKind::Elf(6) => {
-                    let mask = I_TYPE_MASK;
-                    output[index].word &= mask;
-                    output[index].addend &= !mask;
-                    output[index].mask &= mask;
+        for reloc in &section.relocations {
+            let index = (reloc.offset / 4) as usize;
+            let mask = match reloc.kind {
+                RelocationKind::Mips26 => J_TYPE_MASK,
+                // GPREL16 only shows up in GCC objects, which use the small data section
+                RelocationKind::Hi16 | RelocationKind::Lo16 | RelocationKind::Gprel16 => {
+                    I_TYPE_MASK
                 }
-                _ => unimplemented!(),
-            }
+                RelocationKind::Word32 => 0,
+            };
+            let instr = output
+                .get_mut(index)
+                .ok_or_else(|| format!("relocation at 0x{:X} is outside .text", reloc.offset))?;
+            instr.word &= mask;
+            instr.addend &= !mask;
+            instr.mask &= mask;
         }
     }
-    output
+    Ok(output)
 }
 
 fn naive_wordsearch(v: &[u32], pattern: &[u32]) -> Vec<usize> {
     let mut i = 0;
     let mut results = Vec::new();
+    if pattern.len() > v.len() {
+        return results;
+    }
     while i <= v.len() - pattern.len() {
         let mut matches = true;
         for (j, word) in pattern.iter().enumerate() {
@@ -91,19 +152,12 @@ fn naive_wordsearch(v: &[u32], pattern: &[u32]) -> Vec<usize> {
         }
         i += 1;
     }
-    return results;
+    results
 }
 
 fn precise_check(v: &[u32], stencil: &[PreciseStencil]) -> bool {
     assert_eq!(v.len(), stencil.len());
     for (i, instr) in stencil.iter().enumerate() {
-        // println!(
-        //     "{:08X}, {:08X}, {:08X}, {}",
-        //     rom_words[index + i] & instr.1,
-        //     instr.1,
-        //     instr.0,
-        //     rom_words[index + i] & instr.1 != instr.0
-        // );
         if v[i] & instr.mask != instr.word {
             return false;
         }
@@ -111,244 +165,1131 @@ fn precise_check(v: &[u32], stencil: &[PreciseStencil]) -> bool {
     true
 }
 
-// fn print_section_rel<Elf: FileHeader>(
-//     p: &mut Printer<'_>,
-//     endian: Elf::Endian,
-//     data: &[u8],
-//     elf: &Elf,
-//     sections: &SectionTable<Elf>,
-//     section: &Elf::SectionHeader,
-// ) {
-//     if let Some(Some((relocations, link))) = section.rel(endian, data).print_err(p) {
-//         let symbols = sections
-//             .symbol_table_by_index(endian, data, link)
-//             .print_err(p);
-//         let proc = rel_flag_type(endian, elf);
-//         for relocation in relocations {
-//             p.group("Relocation", |p| {
-//                 p.field_hex("Offset", relocation.r_offset(endian).into());
-//                 p.field_enum("Type", relocation.r_type(endian), proc);
-//                 let sym = relocation.r_sym(endian);
-//                 print_rel_symbol(p, endian, symbols, sym);
-//             });
-//         }
-//     }
-// }
-
-// fn print_relocs(obj_file: &object::File) {
-//     let symtab = obj_file.symbol_table().unwrap();
-//     if let Some(section) = obj_file.section_by_name(".text") {
-//         for reloc in section.relocations() {
-//             print!("{:#X}, {:?}: ", reloc.0, reloc.1.kind());
-//             if let RelocationTarget::Symbol(index) = reloc.1.target() {
-//                 println!(
-//                     "{:?}",
-//                     symtab.symbol_by_index(index).unwrap().name().unwrap()
-//                 );
-//             }
-//         }
-//     }
-// }
-
 #[derive(Debug, PartialEq)]
 pub struct FoundFile {
     name: String,
     text_start: usize,
     text_size: usize,
+    anchored: bool, // Found where references put it, rather than by its stencil
+    confidence: Confidence,
+}
+
+/// Where one object's .text was found, and the symbols recovered from there.
+pub struct SignatureMatch {
+    offsets: Vec<usize>,
+    text_size: usize,
+    symbols: Vec<Symbol>,
+    checks: (usize, usize), // Relocated addresses checked against the object's layout, and how many were right
+    gp_relative: Vec<GpReference>,
+}
+
+/// Search the regions for one object. Gives `None` if it could not be searched for at all.
+fn search_signature(
+    regions: &[Region],
+    signature: &Signature,
+    profiles: &Profiles,
+    constraints: &Constraints,
+    skipped: &mut Vec<(String, ObjectError)>,
+) -> Option<SignatureMatch> {
+    let obj_file = &signature.obj;
+    let file_stem = &obj_file.name;
+
+    // Searched for by their data instead
+    if data::data_section(obj_file).is_some() {
+        return None;
+    }
+
+    // print_relocs(&obj_file);
+
+    let Some(section) = obj_file.section_by_name(".text") else {
+        diag::info(
+            Code::NoText,
+            Some(file_stem),
+            format_args!("no .text section found, skipping"),
+        );
+        return None;
+    };
+    let text_size = section.size as usize;
+
+    if text_size == 0 {
+        diag::info(
+            Code::EmptyText,
+            Some(file_stem),
+            format_args!("size-zero .text section, skipping"),
+        );
+        return None;
+    }
+
+    let endian = obj_file.endian;
+    let mut words = Vec::new();
+    let mut rough_stencil = Vec::new();
+
+    words_from_bytes(&section.data, endian, &mut words);
+    if words.iter().all(|elem| *elem == 0) {
+        diag::info(
+            Code::ZeroText,
+            Some(file_stem),
+            format_args!(".text section composed of only zeros, skipping"),
+        );
+        return None;
+    }
+
+    make_rough_stencil(&section.data, endian, &mut rough_stencil);
+    assert_eq!(words.len(), rough_stencil.len());
+
+    let search_range = regions
+        .iter()
+        .filter(|region| region.endian == endian)
+        .map(|region| region.words.len())
+        .max();
+    if let Some(range) = search_range {
+        if range < rough_stencil.len() {
+            let err = ObjectError::TooLarge {
+                size: text_size,
+                range: range * 4,
+            };
+            diag::info(
+                Code::SkippedObject,
+                Some(file_stem),
+                format_args!("skipping, {}", err),
+            );
+            skipped.push((file_stem.to_string(), err));
+            return None;
+        }
+    }
+    let stencil = &signature.stencil;
+
+    let mut precise_results = Vec::new();
+    let mut all_symbols = Vec::new();
+    let mut skipping_symbols = false;
+    let mut checks = (0, 0);
+    let mut gp_relative = Vec::new();
+    for region in regions {
+        if region.endian != endian || region.words.len() < rough_stencil.len() {
+            continue;
+        }
+
+        // Do a rough pass first to quickly narrow down search
+        let rough_results = naive_wordsearch(&region.words, &rough_stencil);
+
+        for result in &rough_results {
+            let index = result / 4;
+            let found_words = &region.words[index..index + stencil.len()];
+
+            if precise_check(found_words, stencil) {
+                let offset = region.offset + result;
+                let vram = region.vram + *result as u32;
+                if let Err(rejection) =
+                    constraints.check(offset, vram, text_size, profiles.alignment(file_stem))
+                {
+                    diag::debug(
+                        Code::PlacementRejected,
+                        Some(file_stem),
+                        format_args!("ignoring match at {:#X}, {}", offset, rejection),
+                    );
+                    continue;
+                }
+                precise_results.push(offset);
+
+                if profiles.is_ambiguous(file_stem) {
+                    if !skipping_symbols {
+                        diag::info(
+                            Code::AmbiguousSymbols,
+                            Some(file_stem),
+                            format_args!("ambiguous, skipping symbols"),
+                        );
+                    }
+                    skipping_symbols = true;
+                    continue;
+                }
+
+                // Symbol parsing
+                let mut symbols =
+                    symbols::parse_symtab_functions(obj_file, file_stem, region.vram, index);
+
+                let relocated = symbols::parse_relocated(
+                    obj_file,
+                    file_stem,
+                    stencil,
+                    found_words,
+                    region.vram + *result as u32,
+                );
+                let (checked, consistent) = confidence::check_relocated(
+                    signature,
+                    region.vram + *result as u32,
+                    &relocated,
+                );
+                checks.0 += checked;
+                checks.1 += consistent;
+                symbols.extend(relocated);
+                gp_relative.extend(symbols::parse_gp_relative(
+                    obj_file,
+                    file_stem,
+                    stencil,
+                    found_words,
+                ));
+
+                // Keep every name at an address, since they may be aliases
+                symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+                symbols.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+
+                // Section symbols are only kept for where the sections start, so name what they point to as well
+                symbols.extend(symbols::parse_section_relative(
+                    obj_file,
+                    file_stem,
+                    stencil,
+                    found_words,
+                    region.vram + *result as u32,
+                ));
+
+                all_symbols.extend(symbols);
+            }
+        }
+    }
+
+    Some(SignatureMatch {
+        offsets: precise_results,
+        text_size,
+        symbols: all_symbols,
+        checks,
+        gp_relative,
+    })
 }
 
-fn disambiguate(
-    rom_words: &[u32],
-    object_paths: Vec<PathBuf>,
-    ambiguous: (String, Vec<u32>),
+/// Score each found file by the symbols recovered so far.
+fn evaluate_found(
+    regions: &[Region],
+    found: &mut [FoundFile],
+    found_signatures: &[&Signature],
+    found_checks: &[(usize, usize)],
     symbols: &[Symbol],
-) -> Vec<FoundFile> {
-    return Vec::new();
+    anchored: &[Anchored],
+) {
+    for ((file, signature), checks) in found.iter_mut().zip(found_signatures).zip(found_checks) {
+        let text_vram = regions
+            .iter()
+            .find_map(|region| region.vram_of(file.text_start))
+            .unwrap();
+        let anchor_score = anchored
+            .iter()
+            .find(|result| result.name == file.name)
+            .map(|result| result.score);
+        file.confidence =
+            confidence::evaluate(signature, text_vram, *checks, symbols, anchor_score);
+    }
 }
 
-/// Write a report containing:
+/// Keep one of each pair of overlapping found files and demote the other to ambiguous, forgetting everything recovered
+/// from it.
+fn demote_overlaps<'a>(
+    found: &mut Vec<FoundFile>,
+    found_signatures: &mut Vec<&'a Signature>,
+    found_checks: &mut Vec<(usize, usize)>,
+    ambiguous: &mut Vec<(String, Vec<usize>)>,
+    ambiguous_signatures: &mut Vec<&'a Signature>,
+    all_symbols: &mut Vec<Symbol>,
+    gp_relative: &mut Vec<GpReference>,
+) -> Vec<Overlap> {
+    let (kept, overlaps) = overlap::resolve(found);
+    for decision in &overlaps {
+        diag::info(
+            Code::OverlappingMatch,
+            Some(&decision.dropped),
+            format_args!(
+                "match at {:#X} overlaps {} at {:#X}, kept {} ({}), demoted this to ambiguous",
+                decision.dropped_start,
+                decision.kept,
+                decision.kept_start,
+                decision.kept,
+                decision.reason
+            ),
+        );
+        ambiguous.push((decision.dropped.clone(), vec![decision.dropped_start]));
+        // Its recovered symbols are only as good as the match
+        all_symbols.retain(|symbol| symbol.filename != decision.dropped);
+        gp_relative.retain(|reference| reference.filename != decision.dropped);
+    }
+    ambiguous_signatures.extend(
+        found_signatures
+            .iter()
+            .zip(&kept)
+            .filter(|(_, kept)| !**kept)
+            .map(|(signature, _)| *signature),
+    );
+    let mut kept_iter = kept.iter();
+    found.retain(|_| *kept_iter.next().unwrap());
+    let mut kept_iter = kept.iter();
+    found_signatures.retain(|_| *kept_iter.next().unwrap());
+    let mut kept_iter = kept.iter();
+    found_checks.retain(|_| *kept_iter.next().unwrap());
+    overlaps
+}
+
+/// Search for every object, collecting:
 /// - unique files (= 1)
 /// - unsure files (> 1)
 /// - not found files (0)
 /// - symbol info
-fn run(romfile: Vec<u8>, object_paths: Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
-    let mut rom_words = Vec::new();
-    let start = 0x1000;
-    let end = start + 0x100000;
-
+///
+/// If the objects come from several versions of a library, each is reported from the version that matched best.
+fn run(
+    regions: &[Region],
+    signatures: &[Signature],
+    profiles: &Profiles,
+    constraints: &Constraints,
+    gp: Option<u32>,
+    mut skipped: Vec<(String, ObjectError)>, // could not be searched for
+) -> Report {
     let mut found = Vec::new(); // length = 1
     let mut ambiguous = Vec::new(); // length > 1
     let mut not_found = Vec::new(); // length = 0
 
     let mut all_symbols = Vec::new();
 
-    words_from_be_bytes(&romfile[start..end], &mut rom_words);
+    let mut found_signatures = Vec::new();
+    let mut found_checks = Vec::new();
+    let mut not_found_signatures = Vec::new();
+    let mut ambiguous_signatures = Vec::new();
+    let mut gp_relative = Vec::new();
 
-    for filepath in object_paths {
-        let file_stem = filepath.file_stem().unwrap().to_string_lossy(); // Maybe
-        let bin_data = fs::read(&filepath)?;
-        let obj_file = object::File::parse(&*bin_data)?;
+    let matches = signatures
+        .iter()
+        .map(|signature| search_signature(regions, signature, profiles, constraints, &mut skipped))
+        .collect::<Vec<_>>();
 
-        // print_relocs(&obj_file);
+    let mut versions = version::detect(signatures, &matches);
+    let selected = version::attribute(&mut versions, signatures, &matches);
 
-        if let Some(section) = obj_file.section_by_name(".text") {
-            let text_size = section.size() as usize;
+    for ((signature, result), selected) in signatures.iter().zip(matches).zip(&selected) {
+        let Some(result) = result else {
+            continue;
+        };
+        if !*selected {
+            continue;
+        }
+        let file_stem = &signature.obj.name;
 
-            if text_size == 0 {
-                eprintln!("{} has a size-zero .text section, skipping", file_stem);
-                continue;
+        match result.offsets.len() {
+            0 => {
+                not_found.push(file_stem.to_string());
+                not_found_signatures.push(signature);
             }
-
-            let mut words = Vec::new();
-            let mut stencil = Vec::new();
-
-            words_from_be_bytes(section.data()?, &mut words);
-            if words.iter().all(|elem| *elem == 0) {
-                eprintln!(
-                    "{} has .text section composed of only zeros, skipping",
-                    file_stem
-                );
-                continue;
+            1 => {
+                found.push(FoundFile {
+                    name: file_stem.to_string(),
+                    text_start: result.offsets[0],
+                    text_size: result.text_size,
+                    anchored: false,
+                    confidence: Confidence::default(),
+                });
+                found_signatures.push(signature);
+                found_checks.push(result.checks);
+                gp_relative.extend(result.gp_relative);
+            }
+            _ => {
+                ambiguous.push((file_stem.to_string(), result.offsets));
+                ambiguous_signatures.push(signature);
             }
+        }
+        all_symbols.extend(result.symbols);
+    }
 
-            // Do a rough pass first to quickly narrow down search
-            make_rough_stencil(section.data()?, &mut stencil);
-            assert_eq!(words.len(), stencil.len());
-            let rough_results = naive_wordsearch(&rom_words, &stencil);
-            if rough_results.len() == 0 {
-                not_found.push(file_stem.to_string());
+    // Only some of an ambiguous file's matches may lie between the files linked before and after it
+    let orders = placement::link_orders(signatures, profiles, constraints.link_order);
+    if !orders.is_empty() {
+        let mut promoted = Vec::new();
+        for (entry, signature) in ambiguous.iter_mut().zip(&ambiguous_signatures) {
+            let bounds = placement::link_order_bounds(&orders, &entry.0, &found);
+            let text_size = signature.obj.section_by_name(".text").unwrap().size as usize;
+            let narrowed = entry
+                .1
+                .iter()
+                .copied()
+                .filter(|&offset| placement::check_link_order(&bounds, offset, text_size).is_ok())
+                .collect::<Vec<_>>();
+            if narrowed.is_empty() || narrowed.len() == entry.1.len() {
                 continue;
             }
+            diag::info(
+                Code::LinkOrder,
+                Some(&entry.0),
+                format_args!(
+                    "link order leaves {} of {} matches",
+                    narrowed.len(),
+                    entry.1.len()
+                ),
+            );
+            if narrowed.len() == 1 {
+                promoted.push((*signature, narrowed[0], text_size));
+            }
+            entry.1 = narrowed;
+        }
 
-            let stencil = make_precise_stencil(&obj_file, section.data()?);
-
-            let mut precise_results = Vec::new();
-            let mut skipping_symbols = false;
-            for result in &rough_results {
-                let index = result / 4;
+        for (signature, text_start, text_size) in promoted {
+            let file_stem = &signature.obj.name;
+            ambiguous.retain(|(name, _)| name != file_stem);
+            ambiguous_signatures.retain(|other| &other.obj.name != file_stem);
+            found.push(FoundFile {
+                name: file_stem.to_string(),
+                text_start,
+                text_size,
+                anchored: false,
+                confidence: Confidence::default(),
+            });
+            found_signatures.push(signature);
+            found_checks.push((0, 0));
+            // Symbols were recovered from every match, so start again from the symbol table
+            let text_vram = regions
+                .iter()
+                .find_map(|region| region.vram_of(text_start))
+                .unwrap();
+            all_symbols.retain(|symbol| &symbol.filename != file_stem);
+            all_symbols.extend(symbols::parse_symtab_functions(
+                &signature.obj,
+                file_stem,
+                text_vram,
+                0,
+            ));
+        }
+    }
 
-                if precise_check(&rom_words[index..index + stencil.len()], &stencil) {
-                    precise_results.push(result + start);
+    // Two files cannot share bytes, so settle which of them is there before going by what they refer to
+    evaluate_found(
+        regions,
+        &mut found,
+        &found_signatures,
+        &found_checks,
+        &all_symbols,
+        &[],
+    );
+    let mut overlaps = demote_overlaps(
+        &mut found,
+        &mut found_signatures,
+        &mut found_checks,
+        &mut ambiguous,
+        &mut ambiguous_signatures,
+        &mut all_symbols,
+        &mut gp_relative,
+    );
 
-                    if libultra::FLAT_AMBIGUOUS_FILES.contains(&&*file_stem) {
-                        if !skipping_symbols {
-                            println!("{file_stem} is ambiguous, skipping symbols");
-                        }
-                        skipping_symbols = true;
-                        continue;
-                    }
+    // Look for what was not found where the found files' references say it is
+    let mut anchored = Vec::new();
+    let mut anchored_symbols = Vec::new();
+    not_found_signatures.retain(|signature| {
+        let Some(result) = anchor::anchored_search(regions, signature, &all_symbols) else {
+            return true;
+        };
+        let file_stem = &signature.obj.name;
+        diag::info(
+            Code::AnchoredSearch,
+            Some(file_stem),
+            format_args!(
+                "{:.0}% of words match at {:#X}, where {} puts {}",
+                result.score * 100.0,
+                result.text_start,
+                result.referenced_by,
+                result.symbol
+            ),
+        );
+        let is_found = result.is_found();
+        if is_found {
+            found.push(FoundFile {
+                name: file_stem.to_string(),
+                text_start: result.text_start,
+                text_size: signature.obj.section_by_name(".text").unwrap().size as usize,
+                anchored: true,
+                confidence: Confidence::default(),
+            });
+            found_signatures.push(signature);
+            found_checks.push((0, 0));
+            not_found.retain(|name| name != file_stem);
+            // Relocated symbols are only as good as the match, so stick to the symbol table
+            anchored_symbols.extend(symbols::parse_symtab_functions(
+                &signature.obj,
+                file_stem,
+                result.text_vram,
+                0,
+            ));
+        }
+        anchored.push(result);
+        !is_found
+    });
+    all_symbols.extend(anchored_symbols);
 
-                    // Symbol parsing
-                    let mut symbols =
-                        symbols::parse_symtab_functions(&obj_file, &file_stem, BASE_ADDRESS, index)
-                            .unwrap();
+    // Anchored matches only avoid each other, so may also overlap
+    evaluate_found(
+        regions,
+        &mut found,
+        &found_signatures,
+        &found_checks,
+        &all_symbols,
+        &anchored,
+    );
+    let anchored_overlaps = demote_overlaps(
+        &mut found,
+        &mut found_signatures,
+        &mut found_checks,
+        &mut ambiguous,
+        &mut ambiguous_signatures,
+        &mut all_symbols,
+        &mut gp_relative,
+    );
+    for result in &mut anchored {
+        result.demoted = anchored_overlaps
+            .iter()
+            .any(|decision| decision.dropped == result.name);
+    }
+    overlaps.extend(anchored_overlaps);
 
-                    symbols.extend(symbols::parse_relocated(
-                        &obj_file,
-                        &file_stem,
-                        &stencil,
-                        &rom_words[index..index + text_size / 4],
-                    )?);
+    // References to small data are relative to $gp, so only give addresses once it is known
+    let gp = match gp {
+        Some(gp) => Some(gp),
+        None => common::infer_gp(&gp_relative, &all_symbols).map(|(gp, count)| {
+            diag::info(
+                Code::GpInferred,
+                None,
+                format_args!("$gp looks to be {:#X}, going by {} references", gp, count),
+            );
+            gp
+        }),
+    };
+    if let Some(gp) = gp {
+        all_symbols.extend(common::resolve_gp(&gp_relative, gp));
+    }
 
-                    symbols.sort_by_key(|x| x.address);
-                    symbols.dedup_by_key(|x| x.address);
+    // Data-only objects have no code to match, so look for their data instead
+    let mut data = Vec::new();
+    for (signature, _) in signatures
+        .iter()
+        .zip(&selected)
+        .filter(|(_, selected)| **selected)
+    {
+        let obj = &signature.obj;
+        if !data::is_data_only(obj) {
+            continue;
+        }
+        let file_stem = &obj.name;
+        // Symbols at fixed addresses, e.g. those of `parameters`, need no searching for
+        all_symbols.extend(symbols::parse_symtab_section(
+            obj,
+            file_stem,
+            SymbolSection::Absolute,
+            0,
+        ));
+        let Some(index) = data::data_section(obj) else {
+            continue;
+        };
 
-                    all_symbols.extend(symbols);
+        let located = match data::search(regions, signature, index, profiles.alignment(file_stem)) {
+            Located::Found(found) => Some(found),
+            Located::Ambiguous(offsets) => {
+                match data::locate_by_reference(regions, signature, index, &offsets, &all_symbols) {
+                    Some(found) => Some(found),
+                    None => {
+                        ambiguous.push((file_stem.to_string(), offsets));
+                        continue;
+                    }
                 }
             }
+            Located::NotFound => {
+                data::locate_by_reference(regions, signature, index, &[], &all_symbols)
+            }
+        };
+        let Some(found) = located else {
+            not_found.push(file_stem.to_string());
+            continue;
+        };
 
-            match precise_results.len() {
-                0 => not_found.push(file_stem.to_string()),
-                1 => found.push(FoundFile {
-                    name: file_stem.to_string(),
-                    text_start: precise_results[0],
-                    text_size: text_size as usize,
-                }),
-                _ => ambiguous.push((file_stem.to_string(), precise_results.clone())),
+        diag::info(
+            Code::DataLocated,
+            Some(file_stem),
+            format_args!(
+                "{} located at {:#X}{}",
+                obj.sections[index].name,
+                found.start,
+                match &found.referenced_by {
+                    Some(file) => format!(", where {} puts it", file),
+                    None => String::new(),
+                }
+            ),
+        );
+        all_symbols.extend(symbols::parse_symtab_section(
+            obj,
+            file_stem,
+            SymbolSection::Section(index),
+            found.vram,
+        ));
+        data.push(found);
+    }
+
+    let selected_signatures = signatures
+        .iter()
+        .zip(&selected)
+        .filter(|(_, selected)| **selected)
+        .map(|(signature, _)| signature)
+        .collect::<Vec<_>>();
+
+    // Pointers in located data, e.g. function tables and jump tables, give the addresses of what they point to
+    for signature in &selected_signatures {
+        let obj = &signature.obj;
+        let file_stem = &obj.name;
+        let found_data = data.iter().find(|found| &found.name == file_stem);
+        if found_data.is_none() && !found.iter().any(|file| &file.name == file_stem) {
+            continue;
+        }
+        for (index, section) in obj.sections.iter().enumerate() {
+            if !matches!(section.kind, SectionKind::Data | SectionKind::ReadOnlyData)
+                || !section
+                    .relocations
+                    .iter()
+                    .any(|reloc| reloc.kind == RelocationKind::Word32)
+            {
+                continue;
+            }
+            let vram = match found_data {
+                Some(found) if data::data_section(obj) == Some(index) => Some(found.vram),
+                _ => data::section_vram(signature, index, &all_symbols),
+            };
+            // Data that is not in the binary, e.g. in a compressed segment, cannot be read
+            let Some(start) =
+                vram.and_then(|vram| regions.iter().find_map(|region| region.offset_of(vram)))
+            else {
+                continue;
+            };
+            match data::parse_pointers(regions, signature, index, start) {
+                Some(pointers) => all_symbols.extend(pointers),
+                None => diag::warning(
+                    Code::DataMismatch,
+                    Some(file_stem),
+                    format_args!(
+                        "{} is not at {:#X}, where references put it",
+                        section.name, start
+                    ),
+                ),
             }
+        }
+    }
 
-            // println!("{}: {:X?} (precise)", file_stem, &precise_results);
-        } else {
-            eprintln!("{}: no .text section found, skipping", file_stem);
+    // Found code gives away where .bss is
+    let bss = bss::infer(&selected_signatures, &all_symbols);
+    for layout in &bss {
+        for reference in &layout.disagreeing {
+            diag::warning(
+                Code::BssInconsistent,
+                Some(&layout.name),
+                format_args!(
+                    "{} puts .bss at {:#X} by {}, but {} other references put it at {:#X}",
+                    reference.referenced_by,
+                    reference.start,
+                    reference.symbol,
+                    layout.agreeing.len(),
+                    layout.start
+                ),
+            );
+        }
+        for other in &layout.overlaps {
+            diag::warning(
+                Code::BssInconsistent,
+                Some(&layout.name),
+                format_args!(".bss at {:#X} overlaps that of {}", layout.start, other),
+            );
         }
+        let signature = selected_signatures
+            .iter()
+            .find(|signature| signature.obj.name == layout.name)
+            .unwrap();
+        all_symbols.extend(symbols::parse_symtab_section(
+            &signature.obj,
+            &layout.name,
+            SymbolSection::Section(layout.section),
+            layout.start,
+        ));
     }
 
-    // return Ok(());
-    println!("Files found:");
-    found.sort_by_key(|k| k.text_start);
-    splat::print_yaml(&found);
-    // for entry in found.iter() {
-    //     println!("{}- [{:#X}, asm, {}]", TAB, entry.text_start, entry.name);
-    // }
+    // COMMON symbols and small data are only placed by references to them
+    let common = common::infer(&selected_signatures, &all_symbols, &gp_relative, gp);
+    for symbol in &common.symbols {
+        for (file, address) in &symbol.disagreeing {
+            diag::warning(
+                Code::CommonInconsistent,
+                Some(&symbol.name),
+                format_args!(
+                    "{} puts it at {:#X}, but others at {:#X}",
+                    file, address, symbol.address
+                ),
+            );
+        }
+        all_symbols.push(Symbol::new(
+            &symbol.name,
+            symbol.address,
+            symbol.size,
+            symbol.defined_in.as_deref().unwrap_or("COMMON"),
+            true,
+        ));
+    }
+
+    // Now that everything that can be recovered has been, score the files by all of it
+    evaluate_found(
+        regions,
+        &mut found,
+        &found_signatures,
+        &found_checks,
+        &all_symbols,
+        &anchored,
+    );
+
+    // Predict where the files that were not placed should be from where the rest are
+    let unplaced = ambiguous_signatures
+        .iter()
+        .chain(&not_found_signatures)
+        .copied()
+        .collect::<Vec<_>>();
+    let layout = layout::infer(&orders, &found, &unplaced, regions, profiles);
+    for name in &layout.out_of_order {
+        diag::info(
+            Code::LinkOrder,
+            Some(name),
+            format_args!("found out of link order with the other files"),
+        );
+    }
 
-    println!("");
-    println!("Ambiguous files:");
+    let likely_present = coverage::likely_present(&found_signatures, &not_found_signatures);
+    for likely in &likely_present {
+        diag::info(
+            Code::LikelyPresent,
+            Some(&likely.name),
+            format_args!(
+                "not found, but defines {} which {} uses",
+                likely.symbol, likely.referenced_by
+            ),
+        );
+    }
+
+    // References only carry the size the referencing file knows, so go by the defining object. Only objects that are
+    // there can say, i.e. found ones, located data and those whose symbols are at fixed addresses.
+    let placed_signatures = selected_signatures
+        .iter()
+        .filter(|signature| {
+            let obj = &signature.obj;
+            found.iter().any(|file| file.name == obj.name)
+                || data.iter().any(|found| found.name == obj.name)
+                || obj.symbols.iter().any(|symbol| {
+                    symbol.section == SymbolSection::Absolute && symbol.is_definition()
+                })
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    for conflict in sizes::infer(&mut all_symbols, &placed_signatures) {
+        let source = match &conflict.defined_in {
+            Some(file) => format!("{} defines it with", file),
+            None => "others give it".to_string(),
+        };
+        for (file, size) in &conflict.disagreeing {
+            diag::warning(
+                Code::SizeConflict,
+                Some(&conflict.name),
+                format_args!(
+                    "{} gives it {:#X} bytes, but {} {:#X}",
+                    file, size, source, conflict.size
+                ),
+            );
+        }
+    }
+
+    found.sort_by_key(|k| k.text_start);
+    data.sort_by_key(|found| found.start);
     ambiguous.sort_by_key(|x| x.1[0]);
-    for entry in ambiguous.iter() {
-        println!(
-            "{}: [ {} ]",
-            entry.0,
-            entry
-                .1
+
+    let symbols = SymbolTable::new(&all_symbols);
+    for conflict in &symbols.conflicts {
+        for (address, files) in &conflict.elsewhere {
+            diag::warning(
+                Code::SymbolConflict,
+                Some(&conflict.name),
+                format_args!(
+                    "{} put it at {:#X}, but {} at {:#X}",
+                    files.join(", "),
+                    address,
+                    conflict.files.join(", "),
+                    conflict.address
+                ),
+            );
+        }
+    }
+
+    Report {
+        start: regions[0].offset,
+        versions,
+        found,
+        data,
+        bss,
+        common,
+        ambiguous,
+        overlaps,
+        not_found,
+        layout,
+        anchored,
+        likely_present,
+        skipped,
+        symbols,
+    }
+}
+
+/// Search a binary for linked objects
+#[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    search: Args,
+    /// Show more diagnostics (repeat for even more)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only show errors
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Work with precompiled signature files
+    #[command(subcommand)]
+    Sigs(SigsCommand),
+    /// Show why an object was or was not found in a binary
+    Explain {
+        /// Name of the object, e.g. osInitialize's "initialize"
+        object: String,
+        #[command(flatten)]
+        binary: BinaryArgs,
+        /// Object files, libraries, signature files, or directories of them that include the object
+        #[arg(required = true)]
+        objects: Vec<PathBuf>,
+        #[command(flatten)]
+        placement: PlacementArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum SigsCommand {
+    /// Compile objects and libraries into a signature file
+    Build {
+        /// Object files, libraries, signature files, or directories of them
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Signature file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Library to tag the signatures with, e.g. libultra
+        #[arg(long)]
+        library: Option<String>,
+        /// Library version to tag the signatures with, e.g. 2.0L
+        #[arg(long)]
+        version: Option<String>,
+    },
+}
+
+/// Which binary to look in, and how to read it.
+#[derive(clap::Args)]
+struct BinaryArgs {
+    /// Binary file to investigate (generally a z64 file)
+    #[arg(required = true)]
+    binary: Option<PathBuf>,
+    /// Kind of binary being searched
+    #[arg(long, value_enum, default_value_t = BinaryKind::Rom)]
+    kind: BinaryKind,
+    /// Address the binary is loaded at: required for blobs, overrides the header entrypoint for ROMs
+    #[arg(long, value_parser = parse_address)]
+    vram: Option<u32>,
+    /// Byte order of the words in a blob (big-endian if not given); other kinds give their own
+    #[arg(long, value_enum)]
+    endian: Option<Endian>,
+    /// Byte order of the ROM image, detected from the header if not given
+    #[arg(long, value_enum)]
+    byte_order: Option<ByteOrder>,
+}
+
+/// What decides where a match may be.
+#[derive(clap::Args)]
+struct PlacementArgs {
+    /// Library profile to use, either built in (libgultra, libultra) or a TOML file; may be given more than once
+    #[arg(long = "profile", default_value = "libgultra")]
+    profiles: Vec<String>,
+    /// Only accept matches within this range of offsets in the binary, e.g. 0x1000-0x80000; may be given more than once
+    #[arg(long = "range", value_parser = parse_range)]
+    ranges: Vec<Range<usize>>,
+    /// Assume files from the same archive or directory were linked in the order given, to narrow down ambiguous ones
+    #[arg(long)]
+    link_order: bool,
+}
+
+#[derive(clap::Args)]
+struct Args {
+    #[command(flatten)]
+    binary: BinaryArgs,
+    /// Object files, libraries, signature files, or directories of them to look for in the binary
+    #[arg(required = true)]
+    objects: Vec<PathBuf>,
+    /// What to write to stdout
+    #[arg(long, value_enum, default_value_t = Format::Report)]
+    format: Format,
+    /// Leave files matched with less confidence than this (0 to 1) out of the splat yaml
+    #[arg(long, default_value_t = 0.0)]
+    min_confidence: f64,
+    #[command(flatten)]
+    placement: PlacementArgs,
+    /// Value of $gp, for placing small data; inferred from the references to it if not given
+    #[arg(long, value_parser = parse_address)]
+    gp: Option<u32>,
+}
+
+fn parse_address(arg: &str) -> Result<u32, std::num::ParseIntError> {
+    let digits = arg.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16)
+}
+
+fn parse_range(arg: &str) -> Result<Range<usize>, String> {
+    let (start, end) = arg
+        .split_once('-')
+        .ok_or("expected a range such as 0x1000-0x80000")?;
+    let start = parse_address(start).map_err(|err| err.to_string())? as usize;
+    let end = parse_address(end).map_err(|err| err.to_string())? as usize;
+    if start >= end {
+        return Err("the start of a range must come before its end".to_string());
+    }
+    Ok(start..end)
+}
+
+fn read_rom(path: &Path, byte_order: Option<ByteOrder>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut romfile = fs::read(path)?;
+    let byte_order = match byte_order.or_else(|| ByteOrder::detect(&romfile)) {
+        Some(order) => order,
+        None => {
+            diag::warning(
+                Code::UnknownRomHeader,
+                None,
+                format_args!(
+                    "{}: unrecognised ROM header, assuming big-endian (use --byte-order to override)",
+                    path.display()
+                ),
+            );
+            ByteOrder::Z64
+        }
+    };
+    rom::normalize(&mut romfile, byte_order);
+    Ok(romfile)
+}
+
+fn read_regions(args: &BinaryArgs) -> Result<Vec<Region>, Box<dyn Error>> {
+    let binary = args.binary.as_ref().expect("required by clap");
+    if args.endian.is_some() && args.kind != BinaryKind::Blob {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--endian only applies to blobs, other kinds of binary give their own byte order",
+            )
+            .exit();
+    }
+
+    let regions = || -> Result<Vec<Region>, Box<dyn Error>> {
+        Ok(match args.kind {
+            BinaryKind::Rom => binary::rom_regions(&read_rom(binary, args.byte_order)?, args.vram)?,
+            BinaryKind::Blob => {
+                let vram = args.vram.ok_or("--vram is required for binary blobs")?;
+                binary::blob_regions(&fs::read(binary)?, vram, args.endian.unwrap_or(Endian::Big))
+            }
+            BinaryKind::Elf => binary::elf_regions(&fs::read(binary)?)?,
+            BinaryKind::Psexe => binary::psexe_regions(&fs::read(binary)?)?,
+        })
+    };
+    regions().map_err(|err| format!("{}: {}", binary.display(), err).into())
+}
+
+/// An error that ends the run, with the code to report it under.
+struct Fatal(Code, Box<dyn Error>);
+
+impl Fatal {
+    fn under(code: Code) -> impl FnOnce(Box<dyn Error>) -> Fatal {
+        move |err| Fatal(code, err)
+    }
+}
+
+impl PlacementArgs {
+    fn load(self) -> Result<(Profiles, Constraints), Fatal> {
+        let profiles = Profiles(
+            self.profiles
                 .iter()
-                .map(|x| format!("{:#X}", x))
-                .collect::<Vec<String>>()
-                .join(", ")
+                .map(|profile| Profile::load(profile))
+                .collect::<Result<_, _>>()
+                .map_err(Fatal::under(Code::InvalidProfile))?,
         );
+        let constraints = Constraints {
+            ranges: self.ranges,
+            link_order: self.link_order,
+        };
+        Ok((profiles, constraints))
     }
+}
 
-    println!("");
-    println!("Files not found:");
-    println!("{}", not_found.join(", "));
+fn search(args: Args) -> Result<(), Fatal> {
+    let regions = read_regions(&args.binary).map_err(Fatal::under(Code::UnreadableBinary))?;
 
-    println!("");
-    println!("Symbols:");
-    all_symbols.sort_by_key(|x| -(x.size as isize));
-    all_symbols.sort_by_key(|x| x.address);
-    all_symbols.dedup_by_key(|x| (x.name.clone(), x.address));
+    let (profiles, constraints) = args.placement.load()?;
 
-    for symbol in all_symbols.iter() {
-        println!(
-            "{}, {:#X}, {:#X}  ({}, {})",
-            symbol.name, symbol.address, symbol.size, symbol.filename, symbol.defined
+    let (signatures, skipped) = sigs::load(&args.objects);
+    for (name, err) in &skipped {
+        diag::info(
+            Code::SkippedObject,
+            Some(name),
+            format_args!("skipping, {}", err),
         );
     }
-    // Uncomment this for splat-compatible symbol output until we have proper argument parsing
-    // splat::print_symbol_addrs(&all_symbols);
 
-    // eprintln!("Found: {:?}", found);
-    // eprintln!("Ambiguous: {:?}", ambiguous);
-    // eprintln!("Not found: {:?}", not_found);
+    let report = run(
+        &regions,
+        &signatures,
+        &profiles,
+        &constraints,
+        args.gp,
+        skipped,
+    );
+    report.print(args.format, &profiles, args.min_confidence);
     Ok(())
 }
 
-fn print_usage() -> () {
-    println!(
-        "\
-    usage: {} BINARY DIRECTORY\n
-    BINARY     binary file to investigate (generally a z64 file)
-    DIRECTORY  directory containing object files to look for in the binary",
-        std::env::args().nth(0).unwrap()
+fn build_signatures(
+    inputs: &[PathBuf],
+    output: &Path,
+    library: Option<String>,
+    version: Option<String>,
+) -> Result<(), Fatal> {
+    let (mut signatures, skipped) = sigs::load(inputs);
+    for signature in &mut signatures {
+        if library.is_some() {
+            signature.library = library.clone();
+        }
+        if version.is_some() {
+            signature.version = version.clone();
+        }
+    }
+    for (name, err) in &skipped {
+        diag::warning(
+            Code::SkippedObject,
+            Some(name),
+            format_args!("not included in signatures, {}", err),
+        );
+    }
+    if signatures.is_empty() {
+        return Err(Fatal(
+            Code::NoObjects,
+            "no objects to build signatures from".into(),
+        ));
+    }
+
+    fs::write(output, sigs::write(&signatures))
+        .map_err(|err| Fatal(Code::UnwritableOutput, err.into()))?;
+    diag::info(
+        Code::SignaturesWritten,
+        None,
+        format_args!(
+            "wrote {} signatures to {}",
+            signatures.len(),
+            output.display()
+        ),
     );
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    if std::env::args().len() == 1 {
-        print_usage();
-        return Ok(());
+fn explain_object(
+    object: &str,
+    binary: &BinaryArgs,
+    objects: &[PathBuf],
+    placement: PlacementArgs,
+) -> Result<(), Fatal> {
+    let regions = read_regions(binary).map_err(Fatal::under(Code::UnreadableBinary))?;
+    let (profiles, constraints) = placement.load()?;
+    let (signatures, skipped) = sigs::load(objects);
+    let matching = signatures
+        .iter()
+        .filter(|signature| signature.obj.name == object)
+        .collect::<Vec<_>>();
+    if matching.is_empty() {
+        // An object that could not be read has nothing to explain but why
+        let stem = |name: &str| {
+            Path::new(name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        };
+        let reason = skipped.iter().find(|(name, err)| match err {
+            ObjectError::InMember { member, .. } => stem(member).as_deref() == Some(object),
+            _ => stem(name).as_deref() == Some(object),
+        });
+        if let Some((name, err)) = reason {
+            return Err(Fatal(
+                Code::SkippedObject,
+                format!("{} was skipped, {} {}", object, name, err).into(),
+            ));
+        }
+        return Err(Fatal(
+            Code::NoObjects,
+            format!("no object named {} in the objects given", object).into(),
+        ));
     }
 
-    // Read and interpret command-line arguments
-    let rompath = std::env::args().nth(1).expect("no rompath given");
-    let objects_dir = std::env::args().nth(2).expect("no objects directory given");
-    let romfile = fs::read(rompath)?;
-    let mut object_paths = fs::read_dir(objects_dir)?
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, io::Error>>()?;
+    // The link order only bounds a file by where the others were found, so those need searching for first
+    let orders = placement::link_orders(&signatures, &profiles, constraints.link_order);
+    let found = if orders.is_empty() {
+        Vec::new()
+    } else {
+        run(
+            &regions,
+            &signatures,
+            &profiles,
+            &constraints,
+            None,
+            Vec::new(),
+        )
+        .found
+    };
+    let bounds = placement::link_order_bounds(&orders, object, &found);
+    for signature in matching {
+        explain::explain(
+            &regions,
+            signature,
+            &constraints,
+            profiles.alignment(object),
+            &bounds,
+        );
+    }
+    Ok(())
+}
 
-    object_paths.sort();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    diag::set_level(diag::level_from_args(cli.verbose, cli.quiet));
 
-    return run(romfile, object_paths);
+    let result = match cli.command {
+        Some(Command::Sigs(SigsCommand::Build {
+            inputs,
+            output,
+            library,
+            version,
+        })) => build_signatures(&inputs, &output, library, version),
+        Some(Command::Explain {
+            object,
+            binary,
+            objects,
+            placement,
+        }) => explain_object(&object, &binary, &objects, placement),
+        None => search(cli.search),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Fatal(code, err)) => {
+            diag::error(code, None, format_args!("{}", err));
+            ExitCode::FAILURE
+        }
+    }
 }
 
 // TODO: write an actual good set of tests
@@ -356,6 +1297,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn first_of_equals() {
+        let items = [("a", 1), ("b", 3), ("c", 3), ("d", 2)];
+        assert_eq!(first_max_by_key(items, |(_, key)| *key), Some(("b", 3)));
+        assert_eq!(first_max_by_key(Vec::<u32>::new(), |item| *item), None);
+    }
+
     #[test]
     fn test() {
         let test_file = vec![];
@@ -365,4 +1313,10 @@ mod tests {
         let test_results = naive_wordsearch(&test_file, &test_pattern);
         assert_eq!(test_results, [0]);
     }
+
+    #[test]
+    fn pattern_longer_than_input() {
+        let test_results = naive_wordsearch(&[0x0C000000], &[0x0C000000, 0x00000000]);
+        assert!(test_results.is_empty());
+    }
 }
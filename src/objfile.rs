@@ -0,0 +1,509 @@
+//! Module for the representation of object files that the search works on. Every supported object format is converted
+//! into this, so the stencils and symbol recovery only need to understand one thing.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use object::{
+    Object, ObjectSection, ObjectSymbol, RelocationTarget, SectionIndex,
+    SectionKind as ElfSectionKind, SymbolKind as ElfSymbolKind, SymbolSection as ElfSymbolSection,
+};
+
+use crate::binary::Endian;
+use crate::{ecoff, psyq};
+
+/// Section index of small common symbols, which GCC puts in .sbss when linking.
+const SHN_MIPS_SCOMMON: u16 = 0xFF03;
+
+/// The section index of an ELF32 symbol, straight from the symbol table.
+fn raw_section_index(symtab: &[u8], index: usize, endian: Endian) -> Option<u16> {
+    let bytes = symtab.get(index * 16 + 14..index * 16 + 16)?;
+    let bytes = [bytes[0], bytes[1]];
+    Some(match endian {
+        Endian::Big => u16::from_be_bytes(bytes),
+        Endian::Little => u16::from_le_bytes(bytes),
+    })
+}
+
+/// Reasons an object cannot be searched for. These only affect the one object, so are reported rather than stopping
+/// the run.
+#[derive(Debug)]
+pub enum ObjectError {
+    Unreadable(io::Error),
+    Unparsable(String),
+    NoSymtab,
+    TooLarge {
+        size: usize,
+        range: usize,
+    },
+    UnsupportedRelocation(String),
+    InMember {
+        member: String,
+        err: Box<ObjectError>,
+    },
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectError::Unreadable(err) => write!(f, "could not be read: {}", err),
+            ObjectError::Unparsable(reason) => write!(f, "could not be parsed: {}", reason),
+            ObjectError::NoSymtab => write!(f, "has no symbol table"),
+            ObjectError::TooLarge { size, range } => write!(
+                f,
+                ".text is {:#X} bytes, larger than the {:#X} bytes searched",
+                size, range
+            ),
+            ObjectError::UnsupportedRelocation(reloc) => {
+                write!(f, "has an unsupported relocation: {}", reloc)
+            }
+            ObjectError::InMember { member, err } => write!(f, "member {} {}", member, err),
+        }
+    }
+}
+
+impl Error for ObjectError {}
+
+impl From<io::Error> for ObjectError {
+    fn from(err: io::Error) -> Self {
+        ObjectError::Unreadable(err)
+    }
+}
+
+impl From<object::Error> for ObjectError {
+    fn from(err: object::Error) -> Self {
+        ObjectError::Unparsable(err.to_string())
+    }
+}
+
+impl From<&str> for ObjectError {
+    fn from(reason: &str) -> Self {
+        ObjectError::Unparsable(reason.to_string())
+    }
+}
+
+impl From<String> for ObjectError {
+    fn from(reason: String) -> Self {
+        ObjectError::Unparsable(reason)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Text,
+    Data,
+    ReadOnlyData,
+    Bss,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    Mips26,
+    Hi16,
+    Lo16,
+    Gprel16,
+    Word32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub offset: u32, // From the start of the section
+    pub kind: RelocationKind,
+    pub symbol: usize, // Index into the object's symbols
+    pub addend: i64,   // Explicit addend; implicit ones are left in the section data
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub size: u32,
+    pub data: Vec<u8>, // Empty for .bss
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Text,
+    Data,
+    Section,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolSection {
+    Undefined,
+    Absolute,
+    Common,
+    Section(usize), // Index into the object's sections
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub section: SymbolSection,
+    pub address: u32, // Offset in the section
+    pub size: u32,
+    pub global: bool,
+}
+
+impl ObjSymbol {
+    /// Whether the object defines the symbol (as opposed to referencing it), in the same sense as ELF.
+    pub fn is_definition(&self) -> bool {
+        self.kind != SymbolKind::Section && self.section != SymbolSection::Undefined
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjFile {
+    pub name: String,
+    pub endian: Endian,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<ObjSymbol>,
+}
+
+impl ObjFile {
+    pub fn section_by_name(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Read an ELF relocatable object.
+    pub fn from_elf(name: &str, data: &[u8]) -> Result<ObjFile, ObjectError> {
+        let elf_file = object::File::parse(data)?;
+        if elf_file.symbol_table().is_none() {
+            return Err(ObjectError::NoSymtab);
+        }
+
+        let mut section_indices = HashMap::new();
+        let mut sections = Vec::new();
+        for section in elf_file.sections() {
+            let kind = match section.kind() {
+                ElfSectionKind::Text => SectionKind::Text,
+                ElfSectionKind::Data => SectionKind::Data,
+                ElfSectionKind::ReadOnlyData | ElfSectionKind::ReadOnlyString => {
+                    SectionKind::ReadOnlyData
+                }
+                ElfSectionKind::UninitializedData => SectionKind::Bss,
+                _ => SectionKind::Other,
+            };
+            section_indices.insert(section.index(), sections.len());
+            sections.push(Section {
+                name: section.name()?.to_string(),
+                kind,
+                size: section.size() as u32,
+                data: section.data()?.to_vec(),
+                relocations: Vec::new(),
+            });
+        }
+        // `object` does not check that symbols are in sections that exist
+        let section_index = |index: SectionIndex| {
+            section_indices
+                .get(&index)
+                .copied()
+                .ok_or_else(|| ObjectError::from(format!("no section {}", index.0)))
+        };
+
+        // Keep the symbol table's indices so relocations can refer to it directly
+        let raw_symtab = match elf_file.section_by_name(".symtab") {
+            Some(section) => section.data()?,
+            None => &[],
+        };
+        let endian = Endian::from(elf_file.endianness());
+        let mut symbols = Vec::new();
+        for symbol in elf_file.symbols() {
+            while symbols.len() < symbol.index().0 {
+                symbols.push(null_symbol());
+            }
+            let section = match symbol.section() {
+                ElfSymbolSection::Section(index) => SymbolSection::Section(section_index(index)?),
+                ElfSymbolSection::Absolute => SymbolSection::Absolute,
+                ElfSymbolSection::Common => SymbolSection::Common,
+                // `object` does not know MIPS's small common section index
+                ElfSymbolSection::Unknown
+                    if raw_section_index(raw_symtab, symbol.index().0, endian)
+                        == Some(SHN_MIPS_SCOMMON) =>
+                {
+                    SymbolSection::Common
+                }
+                _ => SymbolSection::Undefined,
+            };
+            let kind = match symbol.kind() {
+                ElfSymbolKind::Text => SymbolKind::Text,
+                ElfSymbolKind::Data => SymbolKind::Data,
+                ElfSymbolKind::Section => SymbolKind::Section,
+                _ => SymbolKind::Other,
+            };
+            let mut name = symbol.name()?.to_string();
+            // GCC does not name its section symbols
+            if let (SymbolKind::Section, SymbolSection::Section(index)) = (kind, section) {
+                if name.is_empty() {
+                    name = sections[index].name.clone();
+                }
+            }
+            symbols.push(ObjSymbol {
+                name,
+                kind,
+                section,
+                address: symbol.address() as u32,
+                size: symbol.size() as u32,
+                global: symbol.is_global(),
+            });
+        }
+
+        for section in elf_file.sections() {
+            let kind = sections[section_index(section.index())?].kind;
+            if !matches!(
+                kind,
+                SectionKind::Text | SectionKind::Data | SectionKind::ReadOnlyData
+            ) {
+                continue;
+            }
+
+            let mut relocations = Vec::new();
+            for (offset, reloc) in section.relocations() {
+                let reloc_kind = match (reloc.kind(), reloc.size()) {
+                    (object::RelocationKind::Elf(4), _) => RelocationKind::Mips26,
+                    (object::RelocationKind::Elf(5), _) => RelocationKind::Hi16,
+                    (object::RelocationKind::Elf(6), _) => RelocationKind::Lo16,
+                    (object::RelocationKind::Elf(7), _) => RelocationKind::Gprel16,
+                    (object::RelocationKind::Absolute, 32) => RelocationKind::Word32,
+                    (other, _) if kind == SectionKind::Text => {
+                        return Err(ObjectError::UnsupportedRelocation(format!(
+                            "{:?} in .text",
+                            other
+                        )))
+                    }
+                    _ => continue,
+                };
+                let symbol = match reloc.target() {
+                    RelocationTarget::Symbol(index) if index.0 < symbols.len() => index.0,
+                    RelocationTarget::Symbol(_) => {
+                        return Err("relocation targets a missing symbol".into())
+                    }
+                    _ => return Err("relocation does not target a symbol".into()),
+                };
+                relocations.push(Relocation {
+                    offset: offset as u32,
+                    kind: reloc_kind,
+                    symbol,
+                    addend: reloc.addend(),
+                });
+            }
+            sections[section_index(section.index())?].relocations = relocations;
+        }
+
+        Ok(ObjFile {
+            name: name.to_string(),
+            endian,
+            sections,
+            symbols,
+        })
+    }
+}
+
+fn null_symbol() -> ObjSymbol {
+    ObjSymbol {
+        name: String::new(),
+        kind: SymbolKind::Other,
+        section: SymbolSection::Undefined,
+        address: 0,
+        size: 0,
+        global: false,
+    }
+}
+
+const ELF_MAGIC: &[u8] = b"\x7FELF";
+const ARCHIVE_MAGIC: &[u8] = b"!<arch>\n";
+
+/// Read a single object in any of the supported formats.
+fn read_object(name: &str, data: &[u8]) -> Result<ObjFile, ObjectError> {
+    if data.starts_with(ELF_MAGIC) {
+        ObjFile::from_elf(name, data)
+    } else if ecoff::endian(data).is_some() {
+        ecoff::parse(name, data)
+    } else if data.starts_with(psyq::OBJ_MAGIC) {
+        psyq::parse_obj(name, data)
+    } else {
+        Err("unrecognised object file format".into())
+    }
+}
+
+/// Read every member of an `ar` archive. A member that cannot be read is given as an error in its place, the same as
+/// for PSY-Q libraries.
+fn read_archive(data: &[u8]) -> Result<Vec<Result<ObjFile, ObjectError>>, ObjectError> {
+    let archive = object::read::archive::ArchiveFile::parse(data)?;
+    let mut objects = Vec::new();
+    for member in archive.members() {
+        // The members after one with a bad header cannot be found
+        let member = match member {
+            Ok(member) => member,
+            Err(err) => {
+                objects.push(Err(err.into()));
+                break;
+            }
+        };
+        let member_name = String::from_utf8_lossy(member.name()).to_string();
+        let name = Path::new(&member_name).file_stem().unwrap_or_default();
+        let object = member
+            .data(data)
+            .map_err(ObjectError::from)
+            .and_then(|member_data| read_object(&name.to_string_lossy(), member_data))
+            .map_err(|err| ObjectError::InMember {
+                member: member_name.clone(),
+                err: Box::new(err),
+            });
+        objects.push(object);
+    }
+    Ok(objects)
+}
+
+/// Read all the objects in a file, which may be a single object or a library of them. Each member of a library that
+/// cannot be read is given as its own error, so that the rest can still be used.
+pub fn read_objects(
+    path: &Path,
+    data: &[u8],
+) -> Result<Vec<Result<ObjFile, ObjectError>>, ObjectError> {
+    let file_stem = path.file_stem().unwrap().to_string_lossy(); // Maybe
+
+    if data.starts_with(psyq::LIB_MAGIC) {
+        psyq::parse_lib(data)
+    } else if data.starts_with(ARCHIVE_MAGIC) {
+        read_archive(data)
+    } else {
+        Ok(vec![read_object(&file_stem, data)])
+    }
+}
+
+/// Objects made by hand, for the tests of everything that reads them.
+#[cfg(test)]
+pub struct ObjBuilder {
+    obj: ObjFile,
+}
+
+#[cfg(test)]
+impl ObjBuilder {
+    /// A big-endian object with nothing in it.
+    pub fn new(name: &str) -> ObjBuilder {
+        ObjBuilder {
+            obj: ObjFile {
+                name: name.to_string(),
+                endian: Endian::Big,
+                sections: Vec::new(),
+                symbols: Vec::new(),
+            },
+        }
+    }
+
+    /// A section holding `data`, except for .bss, which is only given a size.
+    pub fn section(mut self, name: &str, kind: SectionKind, data: &[u8]) -> ObjBuilder {
+        self.obj.sections.push(Section {
+            name: name.to_string(),
+            kind,
+            size: data.len() as u32,
+            data: if kind == SectionKind::Bss {
+                Vec::new()
+            } else {
+                data.to_vec()
+            },
+            relocations: Vec::new(),
+        });
+        self
+    }
+
+    /// A .text of `words`, in the object's byte order.
+    pub fn text(self, words: &[u32]) -> ObjBuilder {
+        let endian = self.obj.endian;
+        let data = words
+            .iter()
+            .flat_map(|word| match endian {
+                Endian::Big => word.to_be_bytes(),
+                Endian::Little => word.to_le_bytes(),
+            })
+            .collect::<Vec<_>>();
+        self.section(".text", SectionKind::Text, &data)
+    }
+
+    /// A global symbol, of the kind its section holds.
+    pub fn symbol(
+        mut self,
+        name: &str,
+        section: SymbolSection,
+        address: u32,
+        size: u32,
+    ) -> ObjBuilder {
+        let kind = match section {
+            SymbolSection::Section(index) if self.obj.sections[index].kind == SectionKind::Text => {
+                SymbolKind::Text
+            }
+            SymbolSection::Section(_) | SymbolSection::Common => SymbolKind::Data,
+            SymbolSection::Undefined | SymbolSection::Absolute => SymbolKind::Other,
+        };
+        self.obj.symbols.push(ObjSymbol {
+            name: name.to_string(),
+            kind,
+            section,
+            address,
+            size,
+            global: true,
+        });
+        self
+    }
+
+    /// The local symbol for section `index`, which relocations against the section as a whole go through.
+    pub fn section_symbol(mut self, index: usize) -> ObjBuilder {
+        self.obj.symbols.push(ObjSymbol {
+            name: self.obj.sections[index].name.clone(),
+            kind: SymbolKind::Section,
+            section: SymbolSection::Section(index),
+            address: 0,
+            size: 0,
+            global: false,
+        });
+        self
+    }
+
+    /// Make the last symbol added local.
+    pub fn local(mut self) -> ObjBuilder {
+        self.obj.symbols.last_mut().unwrap().global = false;
+        self
+    }
+
+    /// A relocation in the section called `section`, against the first symbol called `name`.
+    pub fn reloc(
+        mut self,
+        section: &str,
+        offset: u32,
+        kind: RelocationKind,
+        name: &str,
+        addend: i64,
+    ) -> ObjBuilder {
+        let symbol = self
+            .obj
+            .symbols
+            .iter()
+            .position(|symbol| symbol.name == name)
+            .unwrap();
+        let section = self
+            .obj
+            .sections
+            .iter_mut()
+            .find(|other| other.name == section)
+            .unwrap();
+        section.relocations.push(Relocation {
+            offset,
+            kind,
+            symbol,
+            addend,
+        });
+        self
+    }
+
+    pub fn build(self) -> ObjFile {
+        self.obj
+    }
+}
@@ -0,0 +1,165 @@
+//! Module for resolving found files that overlap. Nothing stops a tiny handwritten getter from precisely matching inside
+//! a larger function, but two objects cannot occupy the same bytes, so for each overlap one of them is kept and the
+//! other is demoted to ambiguous.
+
+use std::fmt;
+
+use crate::FoundFile;
+
+/// Largest gap between two files that still counts as them being next to each other, since linkers align .text.
+const LAYOUT_SLACK: usize = 0x10;
+
+/// Why one of two overlapping files was kept over the other, in the order they are considered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// It lines up with more of the found files around it
+    Layout,
+    /// It has the higher confidence score
+    Confidence,
+    /// It is larger, so matched more of the binary
+    Size,
+    /// Nothing tells them apart, so the first given was kept
+    Tie,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Layout => "better fit with the layout",
+            Reason::Confidence => "higher confidence",
+            Reason::Size => "larger",
+            Reason::Tie => "indistinguishable, first given",
+        })
+    }
+}
+
+/// Which of two overlapping found files was kept, and why.
+#[derive(Debug, PartialEq)]
+pub struct Overlap {
+    pub kept: String,
+    pub kept_start: usize,
+    pub dropped: String,
+    pub dropped_start: usize,
+    pub reason: Reason,
+}
+
+fn end(file: &FoundFile) -> usize {
+    file.text_start + file.text_size
+}
+
+fn overlaps(a: &FoundFile, b: &FoundFile) -> bool {
+    a.text_start < end(b) && b.text_start < end(a)
+}
+
+/// How many of the file's two ends abut another file still in the layout, ignoring `rival`.
+fn layout_fit(found: &[FoundFile], kept: &[bool], index: usize, rival: usize) -> usize {
+    let file = &found[index];
+    let others = found
+        .iter()
+        .enumerate()
+        .filter(|&(other, _)| kept[other] && other != index && other != rival)
+        .map(|(_, other)| other);
+
+    let previous_end = others
+        .clone()
+        .map(end)
+        .filter(|&other_end| other_end <= file.text_start)
+        .max();
+    let next_start = others
+        .map(|other| other.text_start)
+        .filter(|&other_start| other_start >= end(file))
+        .min();
+
+    let abuts_previous =
+        matches!(previous_end, Some(previous_end) if file.text_start - previous_end < LAYOUT_SLACK);
+    let abuts_next =
+        matches!(next_start, Some(next_start) if next_start - end(file) < LAYOUT_SLACK);
+    abuts_previous as usize + abuts_next as usize
+}
+
+/// Choose between two overlapping files, `a` having been given first. Gives the one to keep and why.
+fn choose(found: &[FoundFile], kept: &[bool], a: usize, b: usize) -> (usize, Reason) {
+    let fits = (layout_fit(found, kept, a, b), layout_fit(found, kept, b, a));
+    if fits.0 != fits.1 {
+        return (if fits.0 > fits.1 { a } else { b }, Reason::Layout);
+    }
+    let scores = (found[a].confidence.score(), found[b].confidence.score());
+    if scores.0 != scores.1 {
+        return (if scores.0 > scores.1 { a } else { b }, Reason::Confidence);
+    }
+    let sizes = (found[a].text_size, found[b].text_size);
+    if sizes.0 != sizes.1 {
+        return (if sizes.0 > sizes.1 { a } else { b }, Reason::Size);
+    }
+    (a, Reason::Tie)
+}
+
+/// Find found files that overlap or contain one another and decide which of each pair to keep. Gives whether each file
+/// was kept, and the decisions made.
+pub fn resolve(found: &[FoundFile]) -> (Vec<bool>, Vec<Overlap>) {
+    let mut kept = vec![true; found.len()];
+    let mut decisions = Vec::new();
+
+    let mut order = (0..found.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| found[index].text_start);
+
+    for (position, &a) in order.iter().enumerate() {
+        for &b in &order[position + 1..] {
+            if found[b].text_start >= end(&found[a]) {
+                break;
+            }
+            if !kept[a] || !kept[b] || !overlaps(&found[a], &found[b]) {
+                continue;
+            }
+            let (first, second) = (a.min(b), a.max(b));
+            let (winner, reason) = choose(found, &kept, first, second);
+            let loser = if winner == first { second } else { first };
+            kept[loser] = false;
+            decisions.push(Overlap {
+                kept: found[winner].name.clone(),
+                kept_start: found[winner].text_start,
+                dropped: found[loser].name.clone(),
+                dropped_start: found[loser].text_start,
+                reason,
+            });
+        }
+    }
+
+    (kept, decisions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::confidence::Confidence;
+
+    fn file(name: &str, text_start: usize, text_size: usize) -> FoundFile {
+        FoundFile {
+            name: name.to_string(),
+            text_start,
+            text_size,
+            anchored: false,
+            confidence: Confidence {
+                words: text_size / 4,
+                bits: text_size as u32 * 8,
+                diversity: 1.0,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn contained_getter_is_dropped() {
+        let found = [
+            file("before", 0x1000, 0x100),
+            file("getter", 0x1140, 0x8),
+            file("function", 0x1100, 0x100),
+            file("after", 0x1200, 0x40),
+        ];
+        let (kept, decisions) = resolve(&found);
+        assert_eq!(kept, [true, false, true, true]);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].kept, "function");
+        assert_eq!(decisions[0].reason, Reason::Layout);
+    }
+}
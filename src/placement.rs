@@ -0,0 +1,161 @@
+//! Module for constraints on where an object can have been placed, which rule out matches the linker could not have
+//! produced: IDO-built objects start on 16-byte boundaries, code only lives in some parts of a binary, and archive
+//! members are linked in the order they appear in the archive (or, failing that, the order a profile gives).
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::profile::Profiles;
+use crate::sigs::Signature;
+use crate::FoundFile;
+
+/// Alignment of a file's .text when its profile does not give one.
+pub const DEFAULT_ALIGNMENT: u32 = 4;
+
+#[derive(Debug, Default)]
+pub struct Constraints {
+    pub ranges: Vec<Range<usize>>, // Offsets in the binary that a match's .text must lie within, if any are given
+    pub link_order: bool,          // Whether files are linked in the order they were given
+}
+
+/// Why a match was ruled out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Misaligned(u32),
+    OutOfRange,
+    OutOfLinkOrder,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Misaligned(alignment) => write!(f, "not aligned to {:#X}", alignment),
+            Rejection::OutOfRange => f.write_str("outside the ranges given"),
+            Rejection::OutOfLinkOrder => {
+                f.write_str("not between the files linked before and after it")
+            }
+        }
+    }
+}
+
+impl Constraints {
+    /// Check a match of `size` bytes of .text at `offset` in the binary, loaded at `vram`.
+    pub fn check(
+        &self,
+        offset: usize,
+        vram: u32,
+        size: usize,
+        alignment: u32,
+    ) -> Result<(), Rejection> {
+        if !vram.is_multiple_of(alignment) {
+            return Err(Rejection::Misaligned(alignment));
+        }
+        if !self.ranges.is_empty()
+            && !self
+                .ranges
+                .iter()
+                .any(|range| range.start <= offset && offset + size <= range.end)
+        {
+            return Err(Rejection::OutOfRange);
+        }
+        Ok(())
+    }
+}
+
+/// Lists of files in the order they were linked. Each profile with an `order` gives one. With `given_order`, so does
+/// each library (or, for untagged files, each input) in the order its files were given, which for an archive is its
+/// member order.
+pub fn link_orders(
+    signatures: &[Signature],
+    profiles: &Profiles,
+    given_order: bool,
+) -> Vec<Vec<String>> {
+    let mut orders = profiles
+        .0
+        .iter()
+        .filter(|profile| !profile.order.is_empty())
+        .map(|profile| profile.order.clone())
+        .collect::<Vec<_>>();
+
+    if given_order {
+        let mut given: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+        for signature in signatures {
+            // Every version of a library is linked in the same order
+            let key = signature
+                .library
+                .as_deref()
+                .or(signature.version.as_deref());
+            let name = &signature.obj.name;
+            match given.iter_mut().find(|(other, _)| *other == key) {
+                Some((_, names)) if !names.contains(name) => names.push(name.clone()),
+                Some(_) => (),
+                None => given.push((key, vec![name.clone()])),
+            }
+        }
+        orders.extend(given.into_iter().map(|(_, names)| names));
+    }
+    orders
+}
+
+/// A file's match can only lie between the found files linked before and after it. Gives the offsets in the binary
+/// that bound where `name`'s .text can be.
+pub fn link_order_bounds(orders: &[Vec<String>], name: &str, found: &[FoundFile]) -> Range<usize> {
+    let mut bounds = 0..usize::MAX;
+    for order in orders {
+        let Some(index) = order.iter().position(|other| other == name) else {
+            continue;
+        };
+        let found_at = |other: &String| found.iter().find(|file| &file.name == other);
+        if let Some(before) = order[..index].iter().rev().find_map(found_at) {
+            bounds.start = bounds.start.max(before.text_start + before.text_size);
+        }
+        if let Some(after) = order[index + 1..].iter().find_map(found_at) {
+            bounds.end = bounds.end.min(after.text_start);
+        }
+    }
+    bounds
+}
+
+/// Check a match of `size` bytes of .text at `offset` in the binary against the bounds from `link_order_bounds`.
+pub fn check_link_order(
+    bounds: &Range<usize>,
+    offset: usize,
+    size: usize,
+) -> Result<(), Rejection> {
+    if bounds.start <= offset && offset + size <= bounds.end {
+        Ok(())
+    } else {
+        Err(Rejection::OutOfLinkOrder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment_and_ranges() {
+        let constraints = Constraints {
+            ranges: vec![0x1000..0x2000, 0x3000..0x3100],
+            link_order: false,
+        };
+        assert_eq!(constraints.check(0x1010, 0x80000410, 0x20, 0x10), Ok(()));
+        assert_eq!(
+            constraints.check(0x1018, 0x80000418, 0x20, 0x10),
+            Err(Rejection::Misaligned(0x10))
+        );
+        assert_eq!(
+            constraints.check(0x1FF0, 0x800013F0, 0x20, 0x10),
+            Err(Rejection::OutOfRange)
+        );
+        assert_eq!(
+            Constraints::default().check(0x5008, 0x80004408, 4, 8),
+            Ok(())
+        );
+        assert_eq!(check_link_order(&(0x1000..0x1040), 0x1020, 0x20), Ok(()));
+        assert_eq!(
+            check_link_order(&(0x1000..0x1040), 0x1024, 0x20),
+            Err(Rejection::OutOfLinkOrder)
+        );
+    }
+}
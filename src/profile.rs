@@ -0,0 +1,175 @@
+//! Module for library profiles, which say what is known about a library's files beyond what their objects do: where
+//! they live in the source, which are handwritten, which are too generic to trust, and which cannot be told apart.
+//!
+//! Profiles are TOML files. The built-in ones are for libgultra and libultra 2.0L, and are only approximate for other
+//! versions; others (NuSystem, libmus, etc.) can be loaded from a path.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::diag::{self, Code};
+use crate::placement;
+
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+    ("libgultra", include_str!("../profiles/libgultra.toml")),
+    ("libultra", include_str!("../profiles/libultra.toml")),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileInfo {
+    pub directory: Option<String>, // In the library's source
+    pub version: Option<String>,   // If it differs from the profile's
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub handwritten: bool,
+    #[serde(default)]
+    pub generic: bool, // Too generic to necessarily believe its specific inclusion
+}
+
+/// Files whose .text is identical, so a match for one is a match for all of them.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AmbiguousGroup {
+    pub files: Vec<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub library: String,
+    pub version: Option<String>,
+    pub notes: Option<String>,
+    pub alignment: Option<u32>, // Of each file's .text, e.g. 16 for IDO-built libraries
+    #[serde(default)]
+    pub ambiguous: Vec<AmbiguousGroup>,
+    #[serde(default)]
+    pub order: Vec<String>, // Files in the order they are linked, if the archive's member order is not to hand
+    #[serde(default)]
+    pub files: BTreeMap<String, FileInfo>,
+}
+
+impl Profile {
+    pub fn builtin(name: &str) -> Option<Profile> {
+        BUILTIN_PROFILES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, text)| toml::from_str(text).expect("built-in profiles are valid"))
+    }
+
+    /// Check what the TOML's shape does not say, i.e. that the values make sense.
+    fn validate(&self) -> Result<(), String> {
+        match self.alignment {
+            Some(alignment) if !alignment.is_power_of_two() => Err(format!(
+                "alignment must be a power of two, not {}",
+                alignment
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Load a built-in profile by name, or otherwise a profile file.
+    pub fn load(name_or_path: &str) -> Result<Profile, Box<dyn Error>> {
+        let profile = match Profile::builtin(name_or_path) {
+            Some(profile) => profile,
+            None => {
+                let text = fs::read_to_string(name_or_path)
+                    .map_err(|err| format!("profile {}: {}", name_or_path, err))?;
+                toml::from_str(&text).map_err(|err| format!("profile {}: {}", name_or_path, err))?
+            }
+        };
+        profile
+            .validate()
+            .map_err(|err| format!("profile {}: {}", name_or_path, err))?;
+        diag::info(
+            Code::ProfileLoaded,
+            None,
+            format_args!(
+                "using profile for {} {}: {}",
+                profile.library,
+                profile.version.as_deref().unwrap_or("(any version)"),
+                profile.notes.as_deref().unwrap_or("no notes")
+            ),
+        );
+        Ok(profile)
+    }
+}
+
+/// All the profiles in use. Lookups go to the first profile that knows about the file.
+pub struct Profiles(pub Vec<Profile>);
+
+impl Profiles {
+    pub fn file(&self, name: &str) -> Option<&FileInfo> {
+        self.0.iter().find_map(|profile| profile.files.get(name))
+    }
+
+    pub fn is_handwritten(&self, name: &str) -> bool {
+        self.file(name).is_some_and(|file| file.handwritten)
+    }
+
+    pub fn is_generic(&self, name: &str) -> bool {
+        self.file(name).is_some_and(|file| file.generic)
+    }
+
+    /// The alignment of a file's .text, from the first profile that knows about the file.
+    pub fn alignment(&self, name: &str) -> u32 {
+        self.0
+            .iter()
+            .find(|profile| profile.files.contains_key(name))
+            .and_then(|profile| profile.alignment)
+            .unwrap_or(placement::DEFAULT_ALIGNMENT)
+    }
+
+    pub fn ambiguous_group(&self, name: &str) -> Option<&AmbiguousGroup> {
+        self.0.iter().find_map(|profile| {
+            profile
+                .ambiguous
+                .iter()
+                .find(|group| group.files.iter().any(|file| file == name))
+        })
+    }
+
+    pub fn is_ambiguous(&self, name: &str) -> bool {
+        self.ambiguous_group(name).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles() {
+        for (name, _) in BUILTIN_PROFILES {
+            assert_eq!(Profile::builtin(name).unwrap().library, *name);
+        }
+
+        let profiles = Profiles(vec![Profile::builtin("libgultra").unwrap()]);
+        assert!(profiles.is_handwritten("bcopy"));
+        assert!(profiles.is_generic("copy"));
+        assert!(profiles.is_ambiguous("seqpdelete"));
+        assert!(!profiles.is_handwritten("mtxf2l"));
+        assert_eq!(profiles.alignment("auxbus"), 16);
+        assert_eq!(profiles.alignment("not_in_libultra"), 4);
+        assert_eq!(
+            profiles.file("auxbus").unwrap().directory.as_deref(),
+            Some("audio")
+        );
+
+        let profiles = Profiles(vec![Profile::builtin("libultra").unwrap()]);
+        assert!(profiles.is_handwritten("mtxf2l"));
+    }
+
+    #[test]
+    fn alignment_must_be_power_of_two() {
+        for (alignment, valid) in [(0, false), (12, false), (1, true), (16, true)] {
+            let text = format!("library = \"nusys\"\nalignment = {}", alignment);
+            let profile: Profile = toml::from_str(&text).unwrap();
+            assert_eq!(profile.validate().is_ok(), valid);
+        }
+    }
+}
@@ -0,0 +1,567 @@
+//! Module for reading the SN Systems object format used by PSY-Q: `.OBJ` files (`LNK` version 2) and the `.LIB`
+//! archives of them. Objects are a stream of records, which are replayed here to build up the sections and symbols.
+
+use std::collections::HashMap;
+
+use crate::binary::Endian;
+use crate::objfile::{
+    ObjFile, ObjSymbol, ObjectError, Relocation, RelocationKind, Section, SectionKind, SymbolKind,
+    SymbolSection,
+};
+
+pub const OBJ_MAGIC: &[u8] = b"LNK\x02";
+pub const LIB_MAGIC: &[u8] = b"LIB\x01";
+
+// Record types
+const END: u8 = 0;
+const CODE: u8 = 2;
+const SWITCH: u8 = 6;
+const UNINITIALISED: u8 = 8;
+const PATCH: u8 = 10;
+const XDEF: u8 = 12;
+const XREF: u8 = 14;
+const SECTION: u8 = 16;
+const LOCAL_SYMBOL: u8 = 18;
+const GROUP_SYMBOL: u8 = 20;
+const FILE_NAME: u8 = 28;
+const CPU: u8 = 46;
+const XBSS: u8 = 48;
+const INC_SLD_LINENUM: u8 = 50;
+const INC_SLD_LINENUM_BY_BYTE: u8 = 52;
+const INC_SLD_LINENUM_BY_WORD: u8 = 54;
+const SET_SLD_LINENUM: u8 = 56;
+const SET_SLD_LINENUM_FILE: u8 = 58;
+const END_SLD: u8 = 60;
+const FUNCTION_START: u8 = 74;
+const FUNCTION_END: u8 = 76;
+const BLOCK_START: u8 = 78;
+const BLOCK_END: u8 = 80;
+const DEF: u8 = 82;
+const DEF2: u8 = 84;
+
+// Patch types. Others, such as those for $gp-relative references, are not known for certain, so objects using them
+// are skipped rather than given a relocation of the wrong kind.
+const PATCH_WORD32: u8 = 16;
+const PATCH_MIPS26: u8 = 74;
+const PATCH_HI16: u8 = 82;
+const PATCH_LO16: u8 = 84;
+
+// Expression operators
+const EXPR_CONSTANT: u8 = 0;
+const EXPR_SYMBOL: u8 = 2;
+const EXPR_SECTION_BASE: u8 = 4;
+const EXPR_SECTION_START: u8 = 12;
+const EXPR_ADD: u8 = 44;
+const EXPR_SUBTRACT: u8 = 46;
+
+struct Reader<'data> {
+    data: &'data [u8],
+    position: usize,
+}
+
+impl<'data> Reader<'data> {
+    fn bytes(&mut self, count: usize) -> Result<&'data [u8], ObjectError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or("unexpected end of PSY-Q object")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ObjectError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ObjectError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, ObjectError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, ObjectError> {
+        let length = self.u8()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).to_string())
+    }
+}
+
+/// What a patch expression resolves to: a symbol number or section number, plus a constant.
+enum Expression {
+    Constant(i64),
+    Symbol(u16, i64),
+    Section(u16, i64),
+}
+
+fn read_expression(reader: &mut Reader) -> Result<Expression, ObjectError> {
+    let operator = reader.u8()?;
+    match operator {
+        EXPR_CONSTANT => Ok(Expression::Constant(reader.u32()? as i64)),
+        EXPR_SYMBOL => Ok(Expression::Symbol(reader.u16()?, 0)),
+        EXPR_SECTION_BASE | EXPR_SECTION_START => Ok(Expression::Section(reader.u16()?, 0)),
+        EXPR_ADD | EXPR_SUBTRACT => {
+            let left = read_expression(reader)?;
+            let right = read_expression(reader)?;
+            let sign = if operator == EXPR_ADD { 1 } else { -1 };
+            match (left, right) {
+                (Expression::Constant(a), Expression::Constant(b)) => {
+                    Ok(Expression::Constant(a + sign * b))
+                }
+                (Expression::Symbol(number, a), Expression::Constant(b)) => {
+                    Ok(Expression::Symbol(number, a + sign * b))
+                }
+                (Expression::Section(number, a), Expression::Constant(b)) => {
+                    Ok(Expression::Section(number, a + sign * b))
+                }
+                (Expression::Constant(a), Expression::Symbol(number, b)) if sign == 1 => {
+                    Ok(Expression::Symbol(number, a + b))
+                }
+                (Expression::Constant(a), Expression::Section(number, b)) if sign == 1 => {
+                    Ok(Expression::Section(number, a + b))
+                }
+                _ => Err(ObjectError::UnsupportedRelocation(
+                    "PSY-Q patch expression".to_string(),
+                )),
+            }
+        }
+        _ => Err(ObjectError::UnsupportedRelocation(format!(
+            "PSY-Q expression operator {}",
+            operator
+        ))),
+    }
+}
+
+fn section_kind(name: &str) -> SectionKind {
+    match name {
+        ".text" => SectionKind::Text,
+        ".data" | ".sdata" | ".ctors" | ".dtors" => SectionKind::Data,
+        ".rdata" => SectionKind::ReadOnlyData,
+        ".bss" | ".sbss" => SectionKind::Bss,
+        _ => SectionKind::Other,
+    }
+}
+
+/// Read a single `LNK` object.
+pub fn parse_obj(name: &str, data: &[u8]) -> Result<ObjFile, ObjectError> {
+    if !data.starts_with(OBJ_MAGIC) {
+        return Err("not a PSY-Q object".into());
+    }
+    let mut reader = Reader {
+        data,
+        position: OBJ_MAGIC.len(),
+    };
+
+    let mut sections: Vec<Section> = Vec::new();
+    let mut symbols = Vec::new();
+    let mut section_numbers = HashMap::new(); // Section number -> index into sections
+    let mut section_symbols = HashMap::new(); // Section number -> index into symbols
+    let mut symbol_numbers = HashMap::new(); // Symbol number -> index into symbols
+    let mut patches = Vec::new(); // (section index, offset, type, expression)
+
+    let mut current = None;
+    let mut code_start = 0; // Patch offsets are from the start of the last code record
+
+    // Sections may only be referred to after they are declared
+    let section_index = |numbers: &HashMap<u16, usize>, number| {
+        numbers
+            .get(&number)
+            .copied()
+            .ok_or_else(|| format!("undeclared PSY-Q section {}", number))
+    };
+
+    loop {
+        match reader.u8()? {
+            END => break,
+            CODE => {
+                let length = reader.u16()? as usize;
+                let bytes = reader.bytes(length)?;
+                let section: &mut Section =
+                    &mut sections[current.ok_or("code outside of a section")?];
+                code_start = section.size;
+                section.data.resize(section.size as usize, 0);
+                section.data.extend_from_slice(bytes);
+                section.size += length as u32;
+            }
+            SWITCH => current = Some(section_index(&section_numbers, reader.u16()?)?),
+            UNINITIALISED => {
+                let length = reader.u32()?;
+                let section: &mut Section =
+                    &mut sections[current.ok_or("data outside of a section")?];
+                section.size += length;
+            }
+            PATCH => {
+                let patch_type = reader.u8()?;
+                let offset = reader.u16()? as u32;
+                let expression = read_expression(&mut reader)?;
+                let section = current.ok_or("patch outside of a section")?;
+                patches.push((section, code_start + offset, patch_type, expression));
+            }
+            XDEF => {
+                let number = reader.u16()?;
+                let section = section_index(&section_numbers, reader.u16()?)?;
+                let address = reader.u32()?;
+                let name = reader.string()?;
+                symbol_numbers.insert(number, symbols.len());
+                symbols.push(ObjSymbol {
+                    name,
+                    kind: symbol_kind(sections[section].kind),
+                    section: SymbolSection::Section(section),
+                    address,
+                    size: 0,
+                    global: true,
+                });
+            }
+            XREF => {
+                let number = reader.u16()?;
+                let name = reader.string()?;
+                symbol_numbers.insert(number, symbols.len());
+                symbols.push(ObjSymbol {
+                    name,
+                    kind: SymbolKind::Other,
+                    section: SymbolSection::Undefined,
+                    address: 0,
+                    size: 0,
+                    global: true,
+                });
+            }
+            SECTION => {
+                let number = reader.u16()?;
+                let _group = reader.u16()?;
+                let _alignment = reader.u8()?;
+                let name = reader.string()?;
+                section_numbers.insert(number, sections.len());
+                section_symbols.insert(number, symbols.len());
+                symbols.push(ObjSymbol {
+                    name: name.clone(),
+                    kind: SymbolKind::Section,
+                    section: SymbolSection::Section(sections.len()),
+                    address: 0,
+                    size: 0,
+                    global: false,
+                });
+                sections.push(Section {
+                    kind: section_kind(&name),
+                    name,
+                    size: 0,
+                    data: Vec::new(),
+                    relocations: Vec::new(),
+                });
+            }
+            LOCAL_SYMBOL => {
+                let section = section_index(&section_numbers, reader.u16()?)?;
+                let address = reader.u32()?;
+                let name = reader.string()?;
+                symbols.push(ObjSymbol {
+                    name,
+                    kind: symbol_kind(sections[section].kind),
+                    section: SymbolSection::Section(section),
+                    address,
+                    size: 0,
+                    global: false,
+                });
+            }
+            GROUP_SYMBOL => {
+                reader.bytes(3)?;
+                reader.string()?;
+            }
+            FILE_NAME => {
+                reader.u16()?;
+                reader.string()?;
+            }
+            CPU => {
+                reader.u8()?;
+            }
+            XBSS => {
+                // Allocated by the linker, like ELF's COMMON
+                let number = reader.u16()?;
+                let _section = reader.u16()?;
+                let size = reader.u32()?;
+                let name = reader.string()?;
+                symbol_numbers.insert(number, symbols.len());
+                symbols.push(ObjSymbol {
+                    name,
+                    kind: SymbolKind::Data,
+                    section: SymbolSection::Common,
+                    address: 0,
+                    size,
+                    global: true,
+                });
+            }
+            // Debugging information, which is of no interest
+            INC_SLD_LINENUM | END_SLD => {
+                reader.u16()?;
+            }
+            INC_SLD_LINENUM_BY_BYTE => {
+                reader.bytes(3)?;
+            }
+            INC_SLD_LINENUM_BY_WORD => {
+                reader.bytes(4)?;
+            }
+            SET_SLD_LINENUM => {
+                reader.bytes(6)?;
+            }
+            SET_SLD_LINENUM_FILE => {
+                // Offset, line number and the number of a file given by a FILE_NAME record
+                reader.bytes(2 + 4 + 2)?;
+            }
+            FUNCTION_START => {
+                reader.bytes(2 + 4 + 2 + 4 + 2 + 4 + 2 + 4 + 4)?;
+                reader.string()?;
+            }
+            FUNCTION_END | BLOCK_START | BLOCK_END => {
+                reader.bytes(2 + 4 + 4)?;
+            }
+            DEF => {
+                reader.bytes(2 + 4 + 2 + 2 + 4)?;
+                reader.string()?;
+            }
+            DEF2 => {
+                reader.bytes(2 + 4 + 2 + 2 + 4)?;
+                let dimensions = reader.u16()? as usize;
+                reader.bytes(4 * dimensions)?;
+                reader.string()?;
+                reader.string()?;
+            }
+            other => return Err(format!("unsupported PSY-Q record type {}", other).into()),
+        }
+    }
+
+    for section in sections.iter_mut() {
+        section.data.resize(section.size as usize, 0);
+        if section.kind == SectionKind::Bss {
+            section.data.clear();
+        }
+    }
+
+    for (section, offset, patch_type, expression) in patches {
+        let kind = match patch_type {
+            PATCH_WORD32 => RelocationKind::Word32,
+            PATCH_MIPS26 => RelocationKind::Mips26,
+            PATCH_HI16 => RelocationKind::Hi16,
+            PATCH_LO16 => RelocationKind::Lo16,
+            _ => {
+                return Err(ObjectError::UnsupportedRelocation(format!(
+                    "PSY-Q patch type {}",
+                    patch_type
+                )))
+            }
+        };
+        let (symbol, addend) = match expression {
+            Expression::Symbol(number, addend) => (
+                *symbol_numbers
+                    .get(&number)
+                    .ok_or_else(|| format!("undeclared PSY-Q symbol {}", number))?,
+                addend,
+            ),
+            Expression::Section(number, addend) => (
+                *section_symbols
+                    .get(&number)
+                    .ok_or_else(|| format!("undeclared PSY-Q section {}", number))?,
+                addend,
+            ),
+            Expression::Constant(_) => return Err("PSY-Q patch has no target".into()),
+        };
+        sections[section].relocations.push(Relocation {
+            offset,
+            kind,
+            symbol,
+            addend,
+        });
+    }
+
+    Ok(ObjFile {
+        name: name.to_string(),
+        endian: Endian::Little,
+        sections,
+        symbols,
+    })
+}
+
+fn symbol_kind(section_kind: SectionKind) -> SymbolKind {
+    match section_kind {
+        SectionKind::Text => SymbolKind::Text,
+        _ => SymbolKind::Data,
+    }
+}
+
+const MODULE_NAME_SIZE: usize = 8;
+
+/// Read every object in a `LIB` archive, named after their modules. A module that cannot be read is given as an error
+/// in its place, and if its header is what is wrong, ends the list since the modules after it cannot be found.
+pub fn parse_lib(data: &[u8]) -> Result<Vec<Result<ObjFile, ObjectError>>, ObjectError> {
+    if !data.starts_with(LIB_MAGIC) {
+        return Err("not a PSY-Q library".into());
+    }
+    let mut objects = Vec::new();
+    let mut module_start = LIB_MAGIC.len();
+
+    while module_start < data.len() {
+        let mut reader = Reader {
+            data,
+            position: module_start,
+        };
+        let mut name = String::new();
+        let module = (|| {
+            name = String::from_utf8_lossy(reader.bytes(MODULE_NAME_SIZE)?)
+                .trim_end()
+                .to_string();
+            let _date = reader.u32()?;
+            let obj_offset = reader.u32()? as usize;
+            let module_size = reader.u32()? as usize;
+            if module_size == 0 {
+                return Err("PSY-Q library module has size zero".into());
+            }
+
+            let module_end = (module_start + module_size).min(data.len());
+            let obj_data = data
+                .get(module_start + obj_offset..module_end)
+                .ok_or("PSY-Q library module is truncated")?;
+            Ok((obj_data, module_size))
+        })();
+        let in_member = |err| ObjectError::InMember {
+            member: name.clone(),
+            err: Box::new(err),
+        };
+
+        match module {
+            Ok((obj_data, module_size)) => {
+                objects.push(parse_obj(&name, obj_data).map_err(in_member));
+                module_start += module_size;
+            }
+            Err(err) => {
+                objects.push(Err(in_member(err)));
+                break;
+            }
+        }
+    }
+
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A function calling `func` and loading the address of a static 4 bytes into `.data`, with line numbers.
+    fn test_obj() -> Vec<u8> {
+        let mut obj = OBJ_MAGIC.to_vec();
+        obj.extend([CPU, 7]);
+        obj.extend([SECTION, 1, 0, 0, 0, 8, 5]);
+        obj.extend(b".text");
+        obj.extend([SECTION, 2, 0, 0, 0, 8, 5]);
+        obj.extend(b".data");
+        obj.extend([XREF, 3, 0, 4]);
+        obj.extend(b"func");
+        obj.extend([XDEF, 4, 0, 1, 0, 0, 0, 0, 0, 6]);
+        obj.extend(b"caller");
+        obj.extend([SWITCH, 2, 0, CODE, 4, 0, 1, 0, 0, 0]);
+        obj.extend([SWITCH, 1, 0, CODE, 16, 0]);
+        obj.extend(0x0C00_0000u32.to_le_bytes()); // jal func
+        obj.extend(0u32.to_le_bytes()); // nop
+        obj.extend(0x3C04_0000u32.to_le_bytes()); // lui a0, %hi
+        obj.extend(0x2484_0000u32.to_le_bytes()); // addiu a0, a0, %lo
+        obj.extend([PATCH, PATCH_MIPS26, 0, 0, EXPR_SYMBOL, 3, 0]);
+        obj.extend([PATCH, PATCH_HI16, 8, 0, EXPR_ADD]);
+        obj.extend([EXPR_CONSTANT, 4, 0, 0, 0, EXPR_SECTION_BASE, 2, 0]);
+        obj.extend([PATCH, PATCH_LO16, 12, 0, EXPR_ADD]);
+        obj.extend([EXPR_CONSTANT, 4, 0, 0, 0, EXPR_SECTION_BASE, 2, 0]);
+        obj.extend([FILE_NAME, 1, 0, 6]);
+        obj.extend(b"test.c");
+        obj.extend([SET_SLD_LINENUM_FILE, 0, 0, 10, 0, 0, 0, 1, 0]);
+        obj.extend([INC_SLD_LINENUM, 4, 0, END_SLD, 16, 0]);
+        obj.extend([END]);
+        obj
+    }
+
+    #[test]
+    fn obj() {
+        let obj = parse_obj("test", &test_obj()).unwrap();
+        assert_eq!(obj.endian, Endian::Little);
+
+        let text = obj.section_by_name(".text").unwrap();
+        assert_eq!(text.kind, SectionKind::Text);
+        assert_eq!(text.size, 16);
+        assert_eq!(&text.data[8..12], &0x3C04_0000u32.to_le_bytes());
+        assert_eq!(obj.section_by_name(".data").unwrap().data, [1, 0, 0, 0]);
+
+        let targets: Vec<_> = text
+            .relocations
+            .iter()
+            .map(|reloc| {
+                (
+                    reloc.offset,
+                    reloc.kind,
+                    obj.symbols[reloc.symbol].name.as_str(),
+                    reloc.addend,
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                (0, RelocationKind::Mips26, "func", 0),
+                (8, RelocationKind::Hi16, ".data", 4),
+                (12, RelocationKind::Lo16, ".data", 4),
+            ]
+        );
+
+        let caller = obj.symbols.iter().find(|s| s.name == "caller").unwrap();
+        assert!(caller.is_definition());
+        assert_eq!(caller.kind, SymbolKind::Text);
+    }
+
+    #[test]
+    fn unknown_patch_types() {
+        for patch_type in [30, 100] {
+            let mut obj = test_obj();
+            // The jal's patch
+            let patch = obj
+                .windows(2)
+                .position(|window| window == [PATCH, PATCH_MIPS26])
+                .unwrap();
+            obj[patch + 1] = patch_type;
+            assert!(matches!(
+                parse_obj("test", &obj),
+                Err(ObjectError::UnsupportedRelocation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn lib() {
+        let obj = test_obj();
+        let mut lib = LIB_MAGIC.to_vec();
+        for name in [b"FIRST   ", b"SECOND  "] {
+            let header_size = MODULE_NAME_SIZE + 12 + 7 + 1;
+            lib.extend(name);
+            lib.extend(0u32.to_le_bytes());
+            lib.extend((header_size as u32).to_le_bytes());
+            lib.extend(((header_size + obj.len()) as u32).to_le_bytes());
+            lib.extend([6]);
+            lib.extend(b"caller");
+            lib.extend([0]);
+            lib.extend(&obj);
+        }
+
+        // Spoil the first module's object, which should not keep the second from being read
+        let mut spoiled = lib.clone();
+        spoiled[LIB_MAGIC.len() + MODULE_NAME_SIZE + 12 + 8] ^= 0xFF;
+
+        let objects = parse_lib(&lib)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].name, "FIRST");
+        assert_eq!(objects[1].name, "SECOND");
+        assert_eq!(objects[1].sections, objects[0].sections);
+
+        let objects = parse_lib(&spoiled).unwrap();
+        assert!(
+            matches!(&objects[0], Err(ObjectError::InMember { member, .. }) if member == "FIRST")
+        );
+        assert_eq!(objects[1].as_ref().unwrap().name, "SECOND");
+    }
+}
@@ -0,0 +1,413 @@
+//! Module for writing the results of a run to stdout, in whichever format was asked for.
+
+use clap::ValueEnum;
+
+use crate::anchor::Anchored;
+use crate::bss::BssLayout;
+use crate::common::{AreaSymbol, CommonArea};
+use crate::coverage::{self, LikelyPresent};
+use crate::data::FoundData;
+use crate::layout::Layout;
+use crate::objfile::{ObjectError, SectionKind};
+use crate::overlap::Overlap;
+use crate::profile::Profiles;
+use crate::symtab::SymbolTable;
+use crate::version::VersionGuess;
+use crate::{splat, FoundFile, TAB};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Everything: found, ambiguous, not found and skipped files, and symbols
+    Report,
+    /// Subsegments for a splat yaml
+    Splat,
+    /// splat's symbol_addrs.txt
+    SymbolAddrs,
+}
+
+pub struct Report {
+    pub start: usize, // Offset of the start of the searched binary
+    pub versions: Vec<VersionGuess>,
+    pub found: Vec<FoundFile>,
+    pub data: Vec<FoundData>, // Data-only files
+    pub bss: Vec<BssLayout>,
+    pub common: CommonArea, // COMMON symbols and small data
+    pub ambiguous: Vec<(String, Vec<usize>)>,
+    pub overlaps: Vec<Overlap>, // Found files that overlapped, and which was demoted to ambiguous
+    pub not_found: Vec<String>,
+    pub layout: Layout, // Where the link order says the files not placed should be
+    pub anchored: Vec<Anchored>, // Not found files checked where references put them
+    pub likely_present: Vec<LikelyPresent>, // Not found, but probably there
+    pub skipped: Vec<(String, ObjectError)>,
+    pub symbols: SymbolTable,
+}
+
+impl Report {
+    pub fn print(&self, format: Format, profiles: &Profiles, min_confidence: f64) {
+        match format {
+            Format::Report => self.print_report(profiles, min_confidence),
+            Format::Splat => splat::print_yaml(
+                &self.found,
+                &self.data,
+                &self.bss,
+                self.start,
+                profiles,
+                min_confidence,
+            ),
+            Format::SymbolAddrs => splat::print_symbol_addrs(&self.symbols),
+        }
+    }
+
+    fn print_report(&self, profiles: &Profiles, min_confidence: f64) {
+        if !self.versions.is_empty() {
+            println!("Library versions:");
+            for guess in self.versions.iter() {
+                print_version(guess);
+            }
+            println!();
+        }
+
+        println!("Files found:");
+        splat::print_yaml(
+            &self.found,
+            &self.data,
+            &self.bss,
+            self.start,
+            profiles,
+            min_confidence,
+        );
+
+        println!();
+        println!("Data found:");
+        for found in self.data.iter() {
+            print!(
+                "{}: {:#X} (vram {:#X}), {:#X} bytes of {}, ",
+                found.name,
+                found.start,
+                found.vram,
+                found.size,
+                if found.section == SectionKind::ReadOnlyData {
+                    "rodata"
+                } else {
+                    "data"
+                }
+            );
+            match &found.referenced_by {
+                Some(file) => println!("where {} puts it", file),
+                None => println!("by contents"),
+            }
+        }
+
+        println!();
+        println!("Bss:");
+        for layout in self.bss.iter() {
+            let mut sources = layout
+                .agreeing
+                .iter()
+                .map(|reference| reference.referenced_by.as_str())
+                .collect::<Vec<_>>();
+            sources.dedup();
+            println!(
+                "{} ({}): {:#X}-{:#X} ({:#X} bytes), from {}",
+                layout.name,
+                layout.section_name,
+                layout.start,
+                layout.end(),
+                layout.size,
+                sources.join(", ")
+            );
+            for reference in layout.disagreeing.iter() {
+                println!(
+                    "{}! {} puts it at {:#X} by {}",
+                    TAB, reference.referenced_by, reference.start, reference.symbol
+                );
+            }
+            if !layout.overlaps.is_empty() {
+                println!("{}! overlaps {}", TAB, layout.overlaps.join(", "));
+            }
+        }
+
+        println!();
+        println!("Common and small data:");
+        self.print_common();
+
+        println!();
+        println!("Confidence:");
+        for file in self.found.iter() {
+            let confidence = &file.confidence;
+            print!(
+                "{}: {:.0}% ({} words, {} bits, {:.0}% distinct, relocations consistent: {} of {}, references from other files: {}",
+                file.name,
+                confidence.score() * 100.0,
+                confidence.words,
+                confidence.bits,
+                confidence.diversity * 100.0,
+                confidence.consistent,
+                confidence.checked,
+                confidence.corroborating
+            );
+            if let Some(anchor_score) = confidence.anchor_score {
+                print!(", anchored at {:.0}%", anchor_score * 100.0);
+            }
+            print!(")");
+            if confidence.score() < min_confidence {
+                print!(" not emitted");
+            }
+            println!();
+        }
+
+        println!();
+        println!("Ambiguous files:");
+        for entry in self.ambiguous.iter() {
+            print!(
+                "{}: [ {} ]",
+                entry.0,
+                entry
+                    .1
+                    .iter()
+                    .map(|x| format!("{:#X}", x))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            match profiles
+                .ambiguous_group(&entry.0)
+                .and_then(|group| group.notes.as_ref())
+            {
+                Some(notes) => println!(" # {}", notes),
+                None => println!(),
+            }
+        }
+
+        println!();
+        println!("Overlapping matches:");
+        for decision in self.overlaps.iter() {
+            println!(
+                "{} at {:#X} overlaps {} at {:#X}: kept {} ({})",
+                decision.dropped,
+                decision.dropped_start,
+                decision.kept,
+                decision.kept_start,
+                decision.kept,
+                decision.reason
+            );
+        }
+
+        println!();
+        println!("Files not found:");
+        println!("{}", self.not_found.join(", "));
+
+        println!();
+        println!("Link order:");
+        self.print_layout();
+
+        println!();
+        println!("Anchored search:");
+        for result in self.anchored.iter() {
+            println!(
+                "{}: {:#X}, where {} puts {}: {:.0}% of words match{}",
+                result.name,
+                result.text_start,
+                result.referenced_by,
+                result.symbol,
+                result.score * 100.0,
+                match (result.is_found(), result.demoted) {
+                    (true, false) => ", found (anchored)",
+                    (true, true) => ", but overlaps a file kept over it",
+                    (false, _) => "",
+                }
+            );
+        }
+
+        println!();
+        println!("Subsystems:");
+        self.print_subsystems(profiles);
+
+        println!();
+        println!("Skipped files:");
+        for (name, reason) in self.skipped.iter() {
+            println!("{}: {}", name, reason);
+        }
+
+        println!();
+        println!("Symbols:");
+        for entry in self.symbols.entries.iter() {
+            let symbol = &entry.symbol;
+            print!(
+                "{}, {:#X}, {:#X}  ({}, {})",
+                symbol.name, symbol.address, symbol.size, symbol.filename, symbol.defined
+            );
+            if entry.aliases.is_empty() {
+                println!();
+            } else {
+                println!(", also {}", entry.aliases.join(", "));
+            }
+            for conflict in self.symbols.conflicts.iter() {
+                if conflict.name != symbol.name || conflict.address != symbol.address {
+                    continue;
+                }
+                for (address, files) in conflict.elsewhere.iter() {
+                    println!("{}! {} put it at {:#X}", TAB, files.join(", "), address);
+                }
+            }
+        }
+    }
+
+    fn print_common(&self) {
+        let common = &self.common;
+        if let Some(gp) = common.gp {
+            println!("$gp: {:#X}", gp);
+        }
+        let mut previous: Option<&AreaSymbol> = None;
+        for symbol in common.symbols.iter() {
+            if let Some(previous) = previous {
+                if symbol.address < previous.end() {
+                    println!("{}! overlaps {}", TAB, previous.name);
+                } else if symbol.address > previous.end() {
+                    println!(
+                        "{}({:#X} bytes unaccounted for)",
+                        TAB,
+                        symbol.address - previous.end()
+                    );
+                }
+            }
+            println!(
+                "{:#X}: {} ({:#X} bytes, {}{}), from {}",
+                symbol.address,
+                symbol.name,
+                symbol.size,
+                match &symbol.defined_in {
+                    Some(file) => format!("defined in {}", file),
+                    None => "common".to_string(),
+                },
+                if symbol.small { ", small" } else { "" },
+                symbol.referenced_by.join(", ")
+            );
+            for (file, address) in symbol.disagreeing.iter() {
+                println!("{}! {} puts it at {:#X}", TAB, file, address);
+            }
+            previous = Some(symbol);
+        }
+        if !common.unplaced.is_empty() {
+            println!("not referenced: {}", common.unplaced.join(", "));
+        }
+    }
+
+    fn print_layout(&self) {
+        if !self.layout.out_of_order.is_empty() {
+            println!("out of order: {}", self.layout.out_of_order.join(", "));
+        }
+        for gap in self.layout.gaps.iter() {
+            println!(
+                "{:#X}-{:#X} ({:#X} bytes), between {} and {}:",
+                gap.start,
+                gap.end,
+                gap.end - gap.start,
+                gap.after,
+                gap.before
+            );
+            for predicted in gap.expected.iter() {
+                println!(
+                    "{}{} at {:#X} ({:#X} bytes)",
+                    TAB, predicted.name, predicted.start, predicted.size
+                );
+            }
+            if !gap.fits() {
+                println!("{}! not all of these fit, so some were not linked", TAB);
+            }
+        }
+    }
+
+    fn print_subsystems(&self, profiles: &Profiles) {
+        let found = self
+            .found
+            .iter()
+            .map(|file| file.name.as_str())
+            .chain(self.data.iter().map(|found| found.name.as_str()))
+            .collect::<Vec<_>>();
+        let ambiguous = self
+            .ambiguous
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        let not_found = self
+            .not_found
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        for subsystem in coverage::subsystems(&found, &ambiguous, &not_found, profiles) {
+            println!(
+                "{}: {} found, {} ambiguous, {} not found ({:.0}% located)",
+                subsystem.name,
+                subsystem.found.len(),
+                subsystem.ambiguous.len(),
+                subsystem.not_found.len(),
+                subsystem.coverage() * 100.0
+            );
+            if !subsystem.found.is_empty() {
+                println!("{}found: {}", TAB, subsystem.found.join(", "));
+            }
+            if !subsystem.ambiguous.is_empty() {
+                println!("{}ambiguous: {}", TAB, subsystem.ambiguous.join(", "));
+            }
+            if !subsystem.not_found.is_empty() {
+                println!("{}not found: {}", TAB, subsystem.not_found.join(", "));
+            }
+            for name in &subsystem.not_found {
+                if let Some(likely) = self
+                    .likely_present
+                    .iter()
+                    .find(|likely| &likely.name == name)
+                {
+                    println!(
+                        "{}! {} is likely present: {} uses its {}",
+                        TAB, name, likely.referenced_by, likely.symbol
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn print_version(guess: &VersionGuess) {
+    print!(
+        "{} {}: {} of {} differing files match ({:.0}% confidence)",
+        guess.library.as_deref().unwrap_or("library"),
+        guess.version,
+        guess.supporting,
+        guess.total,
+        guess.confidence() * 100.0
+    );
+    match &guess.runner_up {
+        Some((version, _)) if guess.is_tied() => println!(", tied with {}", version),
+        Some((version, count)) => println!(", next best {} with {}", version, count),
+        None => println!(),
+    }
+    for item in guess.evidence.iter() {
+        let agrees = item.matched.contains(&guess.version);
+        println!(
+            "{}{} {}: {}",
+            TAB,
+            if agrees { '+' } else { '-' },
+            item.object,
+            item.matched.join(", ")
+        );
+    }
+
+    let mut deviating_versions = guess
+        .deviations
+        .iter()
+        .map(|(_, version)| version)
+        .collect::<Vec<_>>();
+    deviating_versions.sort();
+    deviating_versions.dedup();
+    for version in deviating_versions {
+        let objects = guess
+            .deviations
+            .iter()
+            .filter(|(_, deviation)| deviation == version)
+            .map(|(object, _)| object.as_str())
+            .collect::<Vec<_>>();
+        println!("{}from {}: {}", TAB, version, objects.join(", "));
+    }
+}
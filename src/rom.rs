@@ -0,0 +1,84 @@
+//! Module for handling N64 ROM images. Dumps come in three byte orders, which can be told apart by the first word of the
+//! header (the PI BSD domain 1 configuration, `0x80371240` in every retail ROM).
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ByteOrder {
+    /// Big-endian, the native order of the cartridge
+    Z64,
+    /// 16-bit byteswapped
+    V64,
+    /// 32-bit little-endian
+    N64,
+}
+
+const Z64_MAGIC: [u8; 4] = [0x80, 0x37, 0x12, 0x40];
+const V64_MAGIC: [u8; 4] = [0x37, 0x80, 0x40, 0x12];
+const N64_MAGIC: [u8; 4] = [0x40, 0x12, 0x37, 0x80];
+
+impl ByteOrder {
+    /// Work out the byte order from the header magic, if it is recognised.
+    pub fn detect(rom: &[u8]) -> Option<ByteOrder> {
+        match rom.get(0..4)? {
+            magic if magic == Z64_MAGIC => Some(ByteOrder::Z64),
+            magic if magic == V64_MAGIC => Some(ByteOrder::V64),
+            magic if magic == N64_MAGIC => Some(ByteOrder::N64),
+            _ => None,
+        }
+    }
+}
+
+/// Convert a ROM image in the given byte order to big-endian in place. Any trailing bytes that do not fill a whole
+/// word are left alone.
+pub fn normalize(rom: &mut [u8], order: ByteOrder) {
+    match order {
+        ByteOrder::Z64 => (),
+        ByteOrder::V64 => {
+            for half in rom.chunks_exact_mut(2) {
+                half.swap(0, 1);
+            }
+        }
+        ByteOrder::N64 => {
+            for word in rom.chunks_exact_mut(4) {
+                word.reverse();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: [u8; 8] = [0x80, 0x37, 0x12, 0x40, 0x00, 0x00, 0x00, 0x0F];
+
+    #[test]
+    fn round_trip() {
+        for (order, swapped) in [
+            (
+                ByteOrder::Z64,
+                [0x80, 0x37, 0x12, 0x40, 0x00, 0x00, 0x00, 0x0F],
+            ),
+            (
+                ByteOrder::V64,
+                [0x37, 0x80, 0x40, 0x12, 0x00, 0x00, 0x0F, 0x00],
+            ),
+            (
+                ByteOrder::N64,
+                [0x40, 0x12, 0x37, 0x80, 0x0F, 0x00, 0x00, 0x00],
+            ),
+        ] {
+            let mut rom = swapped;
+            assert_eq!(ByteOrder::detect(&rom), Some(order));
+            normalize(&mut rom, order);
+            assert_eq!(rom, HEADER);
+        }
+    }
+
+    #[test]
+    fn unknown_magic() {
+        assert_eq!(ByteOrder::detect(&[0x7F, b'E', b'L', b'F']), None);
+        assert_eq!(ByteOrder::detect(&[0x80, 0x37]), None);
+    }
+}
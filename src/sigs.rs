@@ -0,0 +1,527 @@
+//! Module for signature files, which hold everything the search needs from a set of objects so that the objects
+//! themselves do not have to be to hand, or parsed again on every run.
+//!
+//! The format is a flat little-endian stream:
+//! - magic, format version and object count
+//! - for each object: name, library and version tags (empty if not given), endianness, sections with their
+//!   relocations, the precise stencil of `.text`, and symbols
+//!
+//! The data of `.text` is not stored, since it is just the stencil's words and addends put back together.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::binary::Endian;
+use crate::diag::{self, Code};
+use crate::objfile::{
+    self, ObjFile, ObjSymbol, ObjectError, Relocation, RelocationKind, Section, SectionKind,
+    SymbolKind, SymbolSection,
+};
+use crate::{make_precise_stencil, PreciseStencil};
+
+const MAGIC: &[u8] = b"FLIBSIGS";
+const FORMAT_VERSION: u16 = 1;
+
+/// An object ready to be searched for, with the library it is from if known.
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    pub obj: ObjFile,
+    pub library: Option<String>,
+    pub version: Option<String>,
+    pub stencil: Vec<PreciseStencil>, // Of .text, empty if there is none
+}
+
+impl Signature {
+    pub fn new(
+        obj: ObjFile,
+        library: Option<String>,
+        version: Option<String>,
+    ) -> Result<Signature, ObjectError> {
+        let stencil = match obj.section_by_name(".text") {
+            Some(section) => make_precise_stencil(&obj, &section.data)?,
+            None => Vec::new(),
+        };
+        Ok(Signature {
+            obj,
+            library,
+            version,
+            stencil,
+        })
+    }
+}
+
+pub fn is_signature_file(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Read signatures from files and directories of files, which may be objects, libraries or signature files. Anything
+/// that cannot be read is returned separately, by file name, down to single members of libraries.
+///
+/// Objects with no version of their own are given the name of the path they were found through, so that e.g. several
+/// extracted SDK directories can be told apart.
+pub fn load(paths: &[PathBuf]) -> (Vec<Signature>, Vec<(String, ObjectError)>) {
+    let mut signatures = Vec::new();
+    let mut skipped = Vec::new();
+
+    let mut file_paths = Vec::new();
+    for path in paths {
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                let input_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string());
+                let mut entries = entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .collect::<Vec<_>>();
+                entries.sort();
+                file_paths.extend(entries.into_iter().map(|entry| (entry, input_name.clone())));
+            }
+            Err(_) => {
+                let input_name = path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string());
+                file_paths.push((path.clone(), input_name));
+            }
+        }
+    }
+
+    for (path, input_name) in file_paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let result = fs::read(&path).map_err(ObjectError::from).and_then(|data| {
+            if is_signature_file(&data) {
+                // Unlike a stray object, a signature file was made to be searched for, so it being unusable is an error
+                let signatures = read(&data).inspect_err(|err| {
+                    diag::error(
+                        Code::BadSignatureFile,
+                        Some(&file_name),
+                        format_args!("{}", err),
+                    )
+                })?;
+                Ok(signatures.into_iter().map(Ok).collect())
+            } else {
+                Ok(objfile::read_objects(&path, &data)?
+                    .into_iter()
+                    .map(|obj| Signature::new(obj?, None, None))
+                    .collect::<Vec<_>>())
+            }
+        });
+        for loaded in result.unwrap_or_else(|err| vec![Err(err)]) {
+            match loaded {
+                Ok(mut signature) => {
+                    if signature.version.is_none() {
+                        signature.version = input_name.clone();
+                    }
+                    signatures.push(signature);
+                }
+                Err(err) => skipped.push((file_name.to_string(), err)),
+            }
+        }
+    }
+
+    (signatures, skipped)
+}
+
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.data.extend(bytes);
+    }
+
+    fn string(&mut self, string: &str) {
+        self.u16(string.len() as u16);
+        self.data.extend(string.as_bytes());
+    }
+}
+
+struct Reader<'data> {
+    data: &'data [u8],
+    position: usize,
+}
+
+impl<'data> Reader<'data> {
+    fn take(&mut self, count: usize) -> Result<&'data [u8], ObjectError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or("unexpected end of signature file")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ObjectError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ObjectError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, ObjectError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, ObjectError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'data [u8], ObjectError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    fn string(&mut self) -> Result<String, ObjectError> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).to_string())
+    }
+
+    fn tag(&mut self) -> Result<Option<String>, ObjectError> {
+        let tag = self.string()?;
+        Ok(if tag.is_empty() { None } else { Some(tag) })
+    }
+}
+
+fn bad_value(what: &str, value: u8) -> ObjectError {
+    format!("signature file has an unknown {} {}", what, value).into()
+}
+
+fn section_kind_to_u8(kind: SectionKind) -> u8 {
+    match kind {
+        SectionKind::Text => 0,
+        SectionKind::Data => 1,
+        SectionKind::ReadOnlyData => 2,
+        SectionKind::Bss => 3,
+        SectionKind::Other => 4,
+    }
+}
+
+fn section_kind_from_u8(value: u8) -> Result<SectionKind, ObjectError> {
+    Ok(match value {
+        0 => SectionKind::Text,
+        1 => SectionKind::Data,
+        2 => SectionKind::ReadOnlyData,
+        3 => SectionKind::Bss,
+        4 => SectionKind::Other,
+        _ => return Err(bad_value("section kind", value)),
+    })
+}
+
+fn relocation_kind_to_u8(kind: RelocationKind) -> u8 {
+    match kind {
+        RelocationKind::Mips26 => 0,
+        RelocationKind::Hi16 => 1,
+        RelocationKind::Lo16 => 2,
+        RelocationKind::Gprel16 => 3,
+        RelocationKind::Word32 => 4,
+    }
+}
+
+fn relocation_kind_from_u8(value: u8) -> Result<RelocationKind, ObjectError> {
+    Ok(match value {
+        0 => RelocationKind::Mips26,
+        1 => RelocationKind::Hi16,
+        2 => RelocationKind::Lo16,
+        3 => RelocationKind::Gprel16,
+        4 => RelocationKind::Word32,
+        _ => return Err(bad_value("relocation kind", value)),
+    })
+}
+
+fn symbol_kind_to_u8(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Text => 0,
+        SymbolKind::Data => 1,
+        SymbolKind::Section => 2,
+        SymbolKind::Other => 3,
+    }
+}
+
+fn symbol_kind_from_u8(value: u8) -> Result<SymbolKind, ObjectError> {
+    Ok(match value {
+        0 => SymbolKind::Text,
+        1 => SymbolKind::Data,
+        2 => SymbolKind::Section,
+        3 => SymbolKind::Other,
+        _ => return Err(bad_value("symbol kind", value)),
+    })
+}
+
+fn write_object(writer: &mut Writer, signature: &Signature) {
+    let obj = &signature.obj;
+    writer.string(&obj.name);
+    writer.string(signature.library.as_deref().unwrap_or_default());
+    writer.string(signature.version.as_deref().unwrap_or_default());
+    writer.u8(match obj.endian {
+        Endian::Big => 0,
+        Endian::Little => 1,
+    });
+
+    writer.u32(obj.sections.len() as u32);
+    for section in &obj.sections {
+        writer.string(&section.name);
+        writer.u8(section_kind_to_u8(section.kind));
+        writer.u32(section.size);
+        writer.bytes(if section.name == ".text" {
+            &[]
+        } else {
+            &section.data
+        });
+        writer.u32(section.relocations.len() as u32);
+        for reloc in &section.relocations {
+            writer.u32(reloc.offset);
+            writer.u8(relocation_kind_to_u8(reloc.kind));
+            writer.u32(reloc.symbol as u32);
+            writer.i64(reloc.addend);
+        }
+    }
+
+    writer.u32(signature.stencil.len() as u32);
+    for instr in &signature.stencil {
+        writer.u32(instr.word);
+        writer.u32(instr.addend);
+        writer.u32(instr.mask);
+    }
+
+    writer.u32(obj.symbols.len() as u32);
+    for symbol in &obj.symbols {
+        writer.string(&symbol.name);
+        writer.u8(symbol_kind_to_u8(symbol.kind));
+        let (section_tag, section_index) = match symbol.section {
+            SymbolSection::Undefined => (0, 0),
+            SymbolSection::Absolute => (1, 0),
+            SymbolSection::Common => (2, 0),
+            SymbolSection::Section(index) => (3, index as u32),
+        };
+        writer.u8(section_tag);
+        writer.u32(section_index);
+        writer.u32(symbol.address);
+        writer.u32(symbol.size);
+        writer.u8(symbol.global as u8);
+    }
+}
+
+fn read_object(reader: &mut Reader) -> Result<Signature, ObjectError> {
+    let name = reader.string()?;
+    let library = reader.tag()?;
+    let version = reader.tag()?;
+    let endian = match reader.u8()? {
+        0 => Endian::Big,
+        1 => Endian::Little,
+        value => return Err(bad_value("endianness", value)),
+    };
+
+    let mut sections = Vec::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.string()?;
+        let kind = section_kind_from_u8(reader.u8()?)?;
+        let size = reader.u32()?;
+        let data = reader.bytes()?.to_vec();
+        let mut relocations = Vec::new();
+        for _ in 0..reader.u32()? {
+            relocations.push(Relocation {
+                offset: reader.u32()?,
+                kind: relocation_kind_from_u8(reader.u8()?)?,
+                symbol: reader.u32()? as usize,
+                addend: reader.i64()?,
+            });
+        }
+        sections.push(Section {
+            name,
+            kind,
+            size,
+            data,
+            relocations,
+        });
+    }
+
+    let mut stencil = Vec::new();
+    for _ in 0..reader.u32()? {
+        stencil.push(PreciseStencil {
+            word: reader.u32()?,
+            addend: reader.u32()?,
+            mask: reader.u32()?,
+        });
+    }
+    if let Some(text) = sections.iter_mut().find(|section| section.name == ".text") {
+        text.data = stencil
+            .iter()
+            .flat_map(|instr| {
+                let word = instr.word | instr.addend;
+                match endian {
+                    Endian::Big => word.to_be_bytes(),
+                    Endian::Little => word.to_le_bytes(),
+                }
+            })
+            .collect();
+    }
+
+    let mut symbols = Vec::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.string()?;
+        let kind = symbol_kind_from_u8(reader.u8()?)?;
+        let section = match (reader.u8()?, reader.u32()?) {
+            (0, _) => SymbolSection::Undefined,
+            (1, _) => SymbolSection::Absolute,
+            (2, _) => SymbolSection::Common,
+            (3, index) if (index as usize) < sections.len() => {
+                SymbolSection::Section(index as usize)
+            }
+            (value, _) => return Err(bad_value("symbol section", value)),
+        };
+        symbols.push(ObjSymbol {
+            name,
+            kind,
+            section,
+            address: reader.u32()?,
+            size: reader.u32()?,
+            global: reader.u8()? != 0,
+        });
+    }
+
+    for section in &sections {
+        if section
+            .relocations
+            .iter()
+            .any(|reloc| reloc.symbol >= symbols.len())
+        {
+            return Err("signature file has a relocation to a missing symbol".into());
+        }
+    }
+
+    // The stencil is indexed by the words relocations are in, so they must all be there
+    for section in &sections {
+        let is_text = section.name == ".text";
+        let length = if is_text {
+            stencil.len() * 4
+        } else {
+            section.data.len()
+        };
+        if section
+            .relocations
+            .iter()
+            .any(|reloc| reloc.offset as usize + 4 > length || (is_text && reloc.offset % 4 != 0))
+        {
+            return Err(format!("signature file has a relocation outside {}", section.name).into());
+        }
+    }
+
+    Ok(Signature {
+        obj: ObjFile {
+            name,
+            endian,
+            sections,
+            symbols,
+        },
+        library,
+        version,
+        stencil,
+    })
+}
+
+/// Write signatures out in the signature file format.
+pub fn write(signatures: &[Signature]) -> Vec<u8> {
+    let mut writer = Writer {
+        data: MAGIC.to_vec(),
+    };
+    writer.u16(FORMAT_VERSION);
+    writer.u32(signatures.len() as u32);
+    for signature in signatures {
+        write_object(&mut writer, signature);
+    }
+    writer.data
+}
+
+/// Read the signatures back out of a signature file.
+pub fn read(data: &[u8]) -> Result<Vec<Signature>, ObjectError> {
+    if !is_signature_file(data) {
+        return Err("not a signature file".into());
+    }
+    let mut reader = Reader {
+        data,
+        position: MAGIC.len(),
+    };
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "signature file format version {} is not supported (expected {})",
+            version, FORMAT_VERSION
+        )
+        .into());
+    }
+
+    let mut signatures = Vec::new();
+    for _ in 0..reader.u32()? {
+        signatures.push(read_object(&mut reader)?);
+    }
+    Ok(signatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::ObjBuilder;
+
+    /// A `jal func` followed by a `nop`, with `func` undefined.
+    fn test_object() -> ObjFile {
+        ObjBuilder::new("caller")
+            .text(&[0x0C000000, 0])
+            .section(".bss", SectionKind::Bss, &[0; 0x10])
+            .symbol("caller", SymbolSection::Section(0), 0, 8)
+            .symbol("func", SymbolSection::Undefined, 0, 0)
+            .reloc(".text", 0, RelocationKind::Mips26, "func", 0)
+            .build()
+    }
+
+    #[test]
+    fn round_trip() {
+        let signatures = vec![
+            Signature::new(
+                test_object(),
+                Some("libultra".to_string()),
+                Some("2.0L".to_string()),
+            )
+            .unwrap(),
+            Signature::new(test_object(), None, None).unwrap(),
+        ];
+        let data = write(&signatures);
+        assert_eq!(read(&data).unwrap(), signatures);
+
+        let mut obj = test_object();
+        obj.sections[0].relocations[0].offset = 8;
+        assert!(Signature::new(obj, None, None).is_err());
+
+        // A corrupt file giving a relocation past the stencil, or in the middle of a word
+        for offset in [8, 2] {
+            let mut signature = Signature::new(test_object(), None, None).unwrap();
+            signature.obj.sections[0].relocations[0].offset = offset;
+            assert!(read(&write(&[signature])).is_err());
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut data = write(&[]);
+        data[MAGIC.len()] = 0xFF;
+        assert!(read(&data).is_err());
+        assert!(read(&data[..MAGIC.len() + 1]).is_err());
+    }
+}
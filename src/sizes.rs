@@ -0,0 +1,186 @@
+//! Module for giving recovered symbols their sizes. A reference only carries the size the referencing file knows,
+//! which is nothing for an undefined symbol, and handwritten files often leave their functions' sizes out. So the
+//! defining object's size is used where there is one, and otherwise where the next symbol in its section starts.
+
+use crate::objfile::{ObjFile, ObjSymbol, SymbolSection};
+use crate::sigs::Signature;
+use crate::symbols::Symbol;
+
+/// Sources that disagree on how large a symbol is.
+#[derive(Debug, PartialEq)]
+pub struct SizeConflict {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,                       // The size used
+    pub defined_in: Option<String>,      // Where it came from, if from the defining object
+    pub disagreeing: Vec<(String, u32)>, // Files that give another size, and what
+}
+
+/// The size of a symbol an object defines, recovered at `address`. If it is not given, it is the distance to the next
+/// symbol in its section (or the end of the section), going by where that symbol was recovered if it was.
+fn defined_size(
+    obj: &ObjFile,
+    defined: &ObjSymbol,
+    address: u32,
+    recovered: &[(String, u32)],
+) -> u32 {
+    if defined.size > 0 {
+        return defined.size;
+    }
+    let SymbolSection::Section(index) = defined.section else {
+        return 0;
+    };
+    let start = address.wrapping_sub(defined.address);
+    let end = start.wrapping_add(obj.sections[index].size);
+    let next = obj
+        .symbols
+        .iter()
+        // Assembler-local labels do not start anything
+        .filter(|other| {
+            other.section == defined.section
+                && other.is_definition()
+                && !other.name.starts_with('.')
+                && other.address > defined.address
+        })
+        .min_by_key(|other| other.address);
+    let Some(next) = next else {
+        return end.wrapping_sub(address);
+    };
+    recovered
+        .iter()
+        .filter(|(name, other)| name == &next.name && address < *other && *other <= end)
+        .map(|(_, other)| *other)
+        .min()
+        .unwrap_or(start.wrapping_add(next.address))
+        .wrapping_sub(address)
+}
+
+/// The object that defines a symbol, preferring the files that refer to it (since statics can share names) to
+/// anything else defining it globally.
+fn defining<'a>(
+    signatures: &[&'a Signature],
+    name: &str,
+    filenames: &[&str],
+) -> Option<(&'a ObjFile, &'a ObjSymbol)> {
+    let definition = |obj: &'a ObjFile, global_only: bool| {
+        obj.symbols.iter().find(|symbol| {
+            // COMMON symbols are only declared, and the linker takes the largest declaration
+            symbol.name == name
+                && symbol.is_definition()
+                && symbol.section != SymbolSection::Common
+                && (symbol.global || !global_only)
+        })
+    };
+    let own = signatures
+        .iter()
+        .filter(|signature| filenames.contains(&signature.obj.name.as_str()))
+        .find_map(|signature| Some((&signature.obj, definition(&signature.obj, false)?)));
+    own.or_else(|| {
+        signatures
+            .iter()
+            .find_map(|signature| Some((&signature.obj, definition(&signature.obj, true)?)))
+    })
+}
+
+/// Give each of `symbols` its size from the defining object among `signatures`, the objects placed in the binary, or
+/// failing that, the largest any reference gives. Section symbols and made-up names are left alone.
+pub fn infer(symbols: &mut [Symbol], signatures: &[&Signature]) -> Vec<SizeConflict> {
+    let mut conflicts = Vec::new();
+
+    let recovered = symbols
+        .iter()
+        .map(|symbol| (symbol.name.clone(), symbol.address))
+        .collect::<Vec<_>>();
+
+    // The same symbol may have been recovered by several files
+    symbols.sort_by(|a, b| (&a.name, a.address).cmp(&(&b.name, b.address)));
+    for same in symbols.chunk_by_mut(|a, b| a.name == b.name && a.address == b.address) {
+        let (name, address) = (same[0].name.clone(), same[0].address);
+        if name.starts_with('.') {
+            continue;
+        }
+        let filenames = same
+            .iter()
+            .map(|symbol| symbol.filename.as_str())
+            .collect::<Vec<_>>();
+        let defined = defining(signatures, &name, &filenames)
+            .map(|(obj, defined)| {
+                let size = defined_size(obj, defined, address, &recovered);
+                (obj.name.clone(), size)
+            })
+            .filter(|(_, size)| *size > 0);
+        let given = same
+            .iter()
+            .filter(|symbol| symbol.size > 0)
+            .map(|symbol| (symbol.filename.clone(), symbol.size))
+            .collect::<Vec<_>>();
+
+        let (size, defined_in) = match defined {
+            Some((file, size)) => (size, Some(file)),
+            None => match given.iter().map(|(_, size)| *size).max() {
+                Some(size) => (size, None),
+                None => continue,
+            },
+        };
+        let mut disagreeing = given
+            .into_iter()
+            .filter(|(_, given)| *given != size)
+            .collect::<Vec<_>>();
+        disagreeing.sort();
+        disagreeing.dedup();
+        if !disagreeing.is_empty() {
+            conflicts.push(SizeConflict {
+                name,
+                address,
+                size,
+                defined_in,
+                disagreeing,
+            });
+        }
+
+        for symbol in same.iter_mut() {
+            symbol.size = size;
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::ObjBuilder;
+
+    #[test]
+    fn size_from_next_symbol() {
+        let text = SymbolSection::Section(0);
+        let obj = ObjBuilder::new("asm")
+            .text(&[0; 0x10])
+            .symbol("first", text, 0, 0)
+            .symbol(".L1", text, 0x8, 0)
+            .local()
+            .symbol("second", text, 0x10, 0)
+            .symbol("sized", text, 0x30, 0x8)
+            .symbol("last", text, 0x38, 0)
+            .build();
+        let recovered = |symbols: &[(&str, u32)]| {
+            symbols
+                .iter()
+                .map(|(name, address)| (name.to_string(), *address))
+                .collect::<Vec<_>>()
+        };
+        // Padding the object does not have, e.g. from a different build, moved second
+        let all = recovered(&[
+            ("first", 0x80000000),
+            ("second", 0x80000018),
+            ("sized", 0x80000030),
+            ("last", 0x80000038),
+        ]);
+        assert_eq!(defined_size(&obj, &obj.symbols[0], 0x80000000, &all), 0x18);
+        assert_eq!(defined_size(&obj, &obj.symbols[2], 0x80000018, &all), 0x18);
+        assert_eq!(defined_size(&obj, &obj.symbols[3], 0x80000030, &all), 0x8);
+        assert_eq!(defined_size(&obj, &obj.symbols[4], 0x80000038, &all), 0x8);
+        // Where the next symbol was not recovered, its offset in the object is all there is
+        let some = recovered(&[("first", 0x80000000)]);
+        assert_eq!(defined_size(&obj, &obj.symbols[0], 0x80000000, &some), 0x10);
+    }
+}
@@ -1,49 +1,147 @@
 // Module for outputting in a splat-compatible format.
 
-use crate::{FoundFile, TAB, symbols::Symbol};
+use crate::bss::BssLayout;
+use crate::data::FoundData;
+use crate::objfile::SectionKind;
+use crate::{profile::Profiles, symtab::SymbolTable, FoundFile, TAB};
 
-use super::libultra;
+/// Something that gets a subsegment of its own.
+enum Entry<'a> {
+    Code(&'a FoundFile),
+    Data(&'a FoundData),
+}
 
-pub fn print_yaml(found_files: &[FoundFile]) {
-    let mut previous_file_text_end = 0x1000;
+impl Entry<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Entry::Code(file) => &file.name,
+            Entry::Data(data) => &data.name,
+        }
+    }
 
-    for entry in found_files {
+    fn start(&self) -> usize {
+        match self {
+            Entry::Code(file) => file.text_start,
+            Entry::Data(data) => data.start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        match self {
+            Entry::Code(file) => file.text_start + file.text_size,
+            Entry::Data(data) => data.start + data.size,
+        }
+    }
+}
+
+pub fn print_yaml(
+    found_files: &[FoundFile],
+    found_data: &[FoundData],
+    bss: &[BssLayout],
+    start: usize,
+    profiles: &Profiles,
+    min_confidence: f64,
+) {
+    let mut previous_file_text_end = start;
+    let mut previous_is_data = false;
+
+    // Files not confident enough in are left to be part of the asm around them
+    let mut entries = found_files
+        .iter()
+        .filter(|entry| entry.confidence.score() >= min_confidence)
+        .map(Entry::Code)
+        .chain(found_data.iter().map(Entry::Data))
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.start());
+
+    for entry in entries {
+        let name = entry.name();
         let mut comment = String::new();
-        let filetype = if libultra::HANDWRITTEN_FILES.contains(&entry.name.as_str()) {
-            "hasm"
-        } else {
-            "c"
+        let filetype = match entry {
+            Entry::Code(_) if profiles.is_handwritten(name) => "hasm",
+            Entry::Code(_) => "c",
+            Entry::Data(data) if data.section == SectionKind::ReadOnlyData => "rodata",
+            Entry::Data(_) => "data",
         };
 
-        if previous_file_text_end < entry.text_start {
-            println!("{}- [{:#X}, asm]", TAB, previous_file_text_end);
+        // Whatever follows code (or starts the segment) is most likely more code, and whatever follows data more data
+        if previous_file_text_end < entry.start() {
+            let gap_type = if previous_is_data { "data" } else { "asm" };
+            println!("{}- [{:#X}, {}]", TAB, previous_file_text_end, gap_type);
         }
 
-        if libultra::GENERIC_FILES.contains(&entry.name.as_str()) {
-            comment.push_str("?");
+        if profiles.is_generic(name) {
+            comment.push('?');
+        }
+        let located = match entry {
+            Entry::Code(file) if file.anchored => Some("anchored"),
+            Entry::Data(data) if data.referenced_by.is_some() => Some("referenced"),
+            _ => None,
+        };
+        if let Some(located) = located {
+            if !comment.is_empty() {
+                comment.push(' ');
+            }
+            comment.push_str(located);
+        }
+        if let Some(file) = profiles.file(name) {
+            for note in [&file.version, &file.notes].into_iter().flatten() {
+                if !comment.is_empty() {
+                    comment.push(' ');
+                }
+                comment.push_str(note);
+            }
         }
 
-        print!(
-            "{}- [{:#X}, {}, {}]",
-            TAB, entry.text_start, filetype, entry.name
-        );
+        print!("{}- [{:#X}, {}, {}]", TAB, entry.start(), filetype, name);
 
-        if comment.len() > 0 {
+        if !comment.is_empty() {
             println!(" # {}", comment);
         } else {
-            println!("");
+            println!();
         }
 
-        previous_file_text_end = entry.text_start + entry.text_size;
+        previous_file_text_end = entry.end();
+        previous_is_data = matches!(entry, Entry::Data(_));
+    }
+
+    // .bss is not in the binary, so goes by vram
+    for layout in bss {
+        print!(
+            "{}- {{ type: {}, vram: {:#X}, name: {} }}",
+            TAB,
+            layout.section_name.trim_start_matches('.'),
+            layout.start,
+            layout.name
+        );
+        if layout.is_consistent() {
+            println!();
+        } else {
+            println!(" # inconsistent");
+        }
     }
 }
 
-pub fn print_symbol_addrs(symbols: &[Symbol]) {
-    for entry in symbols {
-        if entry.name.starts_with('.') {
-            println!("// {}{}+0x0 = {:#X} // size:{:#X}", entry.filename, entry.name, entry.address, entry.size)
+pub fn print_symbol_addrs(symbols: &SymbolTable) {
+    for entry in &symbols.entries {
+        let symbol = &entry.symbol;
+        if symbol.name.starts_with('.') {
+            println!(
+                "// {}{}+0x0 = {:#X} // size:{:#X}",
+                symbol.filename, symbol.name, symbol.address, symbol.size
+            )
         } else {
-            println!("{} = {:#X} // size:{:#X}", entry.name, entry.address, entry.size);
+            println!(
+                "{} = {:#X} // size:{:#X}",
+                symbol.name, symbol.address, symbol.size
+            );
+        }
+        // Other names for the same address
+        for alias in &entry.aliases {
+            println!(
+                "{} = {:#X} // size:{:#X}",
+                alias, symbol.address, symbol.size
+            );
         }
     }
 }
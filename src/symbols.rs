@@ -1,12 +1,9 @@
 //! Module for symbol reading and finding functions. This sort of requires the base vram of the segment; by default we can read this from the rom header.
 
-use std::error::Error;
-
-use object::{
-    Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, RelocationKind, RelocationTarget,
-    SymbolKind,
-};
+use std::collections::HashMap;
 
+use crate::diag::{self, Code};
+use crate::objfile::{ObjFile, RelocationKind, SectionKind, SymbolKind, SymbolSection};
 use crate::{PreciseStencil, I_TYPE_MASK, J_TYPE_MASK};
 
 #[derive(Debug, PartialEq)]
@@ -19,12 +16,135 @@ pub struct Symbol {
     complete: bool,       // LO accounted for
 }
 
-// High bits to use for a jal. Assume this is correct for now, add a user override later
-const HIGH_BITS: u32 = 0x80000000;
+impl Symbol {
+    pub fn new(name: &str, address: u32, size: u32, filename: &str, defined: bool) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            address,
+            size,
+            filename: filename.to_string(),
+            defined,
+            complete: true,
+        }
+    }
+
+    /// Whether the address is all there, i.e. a HI16 has had its LO16 added.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The same symbol as recovered from a HI16 whose LO16 has not been seen.
+    #[cfg(test)]
+    pub fn without_lo16(mut self) -> Symbol {
+        self.complete = false;
+        self
+    }
+}
+
+/// A reference relative to $gp, whose value the code alone does not give.
+#[derive(Debug, PartialEq)]
+pub struct GpReference {
+    pub name: String,
+    pub filename: String,
+    pub offset: i32, // From $gp
+}
+
+// High bits of a jal target come from the address of the jal itself
+const J_TYPE_REGION_MASK: u32 = 0xF0_00_00_00;
+
+/// An address a relocation in a match gives.
+struct Relocated {
+    symbol: usize, // Index in the object's symbols
+    target: u32,   // The address in the match, which has the addends in it
+    addend: u32,   // The addend in the instruction (or instructions) and in the relocation
+    complete: bool,
+}
+
+/// The addresses relocations in a match's .text give. A LO16 is added to the latest HI16 against the same symbol:
+/// GCC can pair several LO16s with one HI16, and schedule HI16s for other symbols in between. A HI16 only gives an
+/// incomplete address of its own if no LO16 follows it.
+fn relocated_addresses(
+    obj_file: &ObjFile,
+    filename: &str,
+    stencil: &[PreciseStencil],
+    rom_words: &[u32], // Starting from the correct index
+    vram: u32,         // Address of the start of the .text
+) -> Vec<Relocated> {
+    let mut addresses = Vec::new();
+    assert_eq!(stencil.len(), rom_words.len());
 
-const R_MIPS_26: u32 = 4;
-const R_MIPS_HI16: u32 = 5;
-const R_MIPS_LO16: u32 = 6;
+    let Some(section) = obj_file.section_by_name(".text") else {
+        return addresses;
+    };
+    // The latest HI16 of each symbol: its index in `addresses` and the upper halves of the address and addend
+    let mut his = HashMap::new();
+    for reloc in &section.relocations {
+        let index = (reloc.offset / 4) as usize;
+        let word = rom_words[index];
+        let addend = stencil[index].addend;
+
+        let (target, addend) = match reloc.kind {
+            RelocationKind::Mips26 => {
+                // Ignore js since are usually just GCC's version of a b
+                if word & J_TYPE_MASK == 0b000010 << 26 {
+                    continue;
+                }
+                let target = (vram & J_TYPE_REGION_MASK) + ((word & !J_TYPE_MASK) << 2);
+                (target, (addend << 2).wrapping_add(reloc.addend as u32))
+            }
+            RelocationKind::Hi16 => {
+                let (upper, upper_addend) = ((word & !I_TYPE_MASK) << 16, addend << 16);
+                his.insert(reloc.symbol, (addresses.len(), upper, upper_addend));
+                addresses.push(Relocated {
+                    symbol: reloc.symbol,
+                    target: upper,
+                    addend: upper_addend,
+                    complete: false,
+                });
+                continue;
+            }
+            RelocationKind::Lo16 => {
+                let Some(&(hi_index, upper, upper_addend)) = his.get(&reloc.symbol) else {
+                    diag::debug(
+                        Code::UnpairedLo16,
+                        Some(filename),
+                        format_args!(
+                            "LO16 for {} at {:#X} has no HI16 before it",
+                            obj_file.symbols[reloc.symbol].name, reloc.offset
+                        ),
+                    );
+                    continue;
+                };
+                // The low half is sign-extended when it is added
+                let relocated = Relocated {
+                    symbol: reloc.symbol,
+                    target: upper.wrapping_add((word & !I_TYPE_MASK) as i16 as u32),
+                    addend: upper_addend
+                        .wrapping_add(addend as i16 as u32)
+                        .wrapping_add(reloc.addend as u32),
+                    complete: true,
+                };
+                // The first LO16 completes the HI16's address, and any after it give their own
+                if addresses[hi_index].complete {
+                    addresses.push(relocated);
+                } else {
+                    addresses[hi_index] = relocated;
+                }
+                continue;
+            }
+            RelocationKind::Word32 => (word, addend.wrapping_add(reloc.addend as u32)),
+            // The value of $gp is not known, so these give no address
+            RelocationKind::Gprel16 => continue,
+        };
+        addresses.push(Relocated {
+            symbol: reloc.symbol,
+            target,
+            addend,
+            complete: true,
+        });
+    }
+    addresses
+}
 
 /// Idea:
 /// - parse .text section as usual, but separate off the relocated parts from the stencil instead of discarding them?
@@ -35,128 +155,251 @@ const R_MIPS_LO16: u32 = 6;
 ///     - Addend
 /// - Get the addresses of the functions out of the rom blob.
 pub fn parse_relocated(
-    obj_file: &object::File,
+    obj_file: &ObjFile,
     filename: &str,
-    stencil: &Vec<PreciseStencil>,
+    stencil: &[PreciseStencil],
     rom_words: &[u32], // Starting from the correct index
-) -> Result<Vec<Symbol>, Box<dyn Error>> {
-    let mut symbols = Vec::new();
-    assert_eq!(stencil.len(), rom_words.len());
-
-    if let Some(section) = obj_file.section_by_name(".text") {
-        for (offset, reloc) in section.relocations() {
-            let index = (offset / 4) as usize;
-
-            let mut name = "Unknown".to_string();
-            let mut size = 0;
-            let mut defined = false;
-
-            match reloc.target() {
-                RelocationTarget::Symbol(sym_index) => {
-                    let symbol = obj_file.symbol_by_index(sym_index).unwrap();
-                    name = symbol.name()?.to_string();
-                    size = symbol.size() as u32;
-                    defined = symbol.is_definition();
-                }
-                _ => (),
-            };
-
-            // if &name == &"osRomType".to_string() {
-            //     for sym in obj_file.symbols() {
-            //         println!("{}", sym.name().unwrap());
-            //     }
-            //     println!("{:?}", reloc);
-            // }
-
-            match reloc.kind() {
-                RelocationKind::Elf(R_MIPS_26) => {
-                    // Ignore js since are usually just GCC's version of a b
-                    if rom_words[index] & J_TYPE_MASK != 0b000010 << 26 {
-                        // println!("{:?}", reloc.addend());
-                        let mut address = HIGH_BITS + ((rom_words[index] & !J_TYPE_MASK) << 2);
-                        address -= stencil[index].addend;
-                        symbols.push(Symbol {
-                            name: name.to_string(),
-                            address,
-                            size,
-                            filename: filename.to_string(),
-                            defined,
-                            complete: true,
-                        });
-                    }
-                }
-                RelocationKind::Elf(R_MIPS_HI16) => {
-                    let mut address = (rom_words[index] & !I_TYPE_MASK) << 16;
-                    address -= stencil[index].addend << 16;
-                    symbols.push(Symbol {
-                        name: name.to_string(),
-                        address,
-                        size,
-                        filename: filename.to_string(),
-                        defined,
-                        complete: false,
-                    });
-                }
-                RelocationKind::Elf(R_MIPS_LO16) => {
-                    let address = rom_words[index] & !I_TYPE_MASK;
-
-                    if let Some(last_symbol) = symbols.last_mut() {
-                        if !last_symbol.complete {
-                            last_symbol.address += address + ((address & 0x8000) << 1);
-                            last_symbol.address -= reloc.addend() as u32;
-                            last_symbol.address -= stencil[index].addend;
-                            last_symbol.complete = true;
-                            // if reloc.addend() != 0 {
-                            //     println!("{:?}", last_symbol);
-                            //     println!("{:?}", reloc.addend());
-                            // }
-                            // if stencil[index].addend != 0 {
-                            //     println!("{:?}", last_symbol);
-                            //     println!("{:?}", stencil[index].addend);
-                            // }
-                        } else {
-                            println!("Last symbol seems complete already");
-                            println!("{:?}", last_symbol);
-                            println!("{:?}", name);
-                            println!("{:?}", reloc);
-                        }
-                    }
-                }
-                _ => unimplemented!(),
+    vram: u32,         // Address of the start of the .text
+) -> Vec<Symbol> {
+    relocated_addresses(obj_file, filename, stencil, rom_words, vram)
+        .into_iter()
+        .map(|relocated| {
+            let symbol = &obj_file.symbols[relocated.symbol];
+            Symbol {
+                name: symbol.name.clone(),
+                address: relocated.target.wrapping_sub(relocated.addend),
+                size: symbol.size,
+                filename: filename.to_string(),
+                defined: symbol.is_definition(),
+                complete: relocated.complete,
             }
-        }
-    }
-    Ok(symbols)
+        })
+        .collect()
 }
 
 pub fn parse_symtab_functions(
-    obj_file: &object::File,
+    obj_file: &ObjFile,
     filename: &str,
     base_address: u32,
     index: usize,
-) -> Result<Vec<Symbol>, Box<dyn Error>> {
+) -> Vec<Symbol> {
     let mut symbols = Vec::new();
-    // if let text_index = obj_file.section_by_name(".text").unwrap().index() {
-    for sym in obj_file.symbol_table().unwrap().symbols() {
-        if sym.kind() == SymbolKind::Text && sym.is_definition() {
-            // println!(
-            //     "{} : {} : {:#X} ({:?})",
-            //     filename,
-            //     sym.name().unwrap(),
-            //     sym.address(),
-            //     sym.section()
-            // );
+    for sym in &obj_file.symbols {
+        if sym.kind == SymbolKind::Text && sym.is_definition() {
             symbols.push(Symbol {
-                name: sym.name().unwrap().to_string(),
-                address: base_address + (index as u32) * 4 + sym.address() as u32,
-                size: sym.size() as u32,
+                name: sym.name.clone(),
+                address: base_address + (index as u32) * 4 + sym.address,
+                size: sym.size,
                 filename: filename.to_string(),
                 defined: sym.is_definition(),
                 complete: true,
             });
         }
     }
-    // }
 
-    Ok(symbols)
+    symbols
+}
+
+/// Symbols defined in one section of an object (or at absolute addresses), with the section loaded at `base_address`.
+pub fn parse_symtab_section(
+    obj_file: &ObjFile,
+    filename: &str,
+    section: SymbolSection,
+    base_address: u32,
+) -> Vec<Symbol> {
+    obj_file
+        .symbols
+        .iter()
+        .filter(|sym| sym.section == section && sym.is_definition())
+        .map(|sym| Symbol {
+            name: sym.name.clone(),
+            address: base_address.wrapping_add(sym.address),
+            size: sym.size,
+            filename: filename.to_string(),
+            defined: true,
+            complete: true,
+        })
+        .collect()
+}
+
+/// References relative to $gp in a match, i.e. to small data.
+pub fn parse_gp_relative(
+    obj_file: &ObjFile,
+    filename: &str,
+    stencil: &[PreciseStencil],
+    rom_words: &[u32], // Starting from the correct index
+) -> Vec<GpReference> {
+    let mut references = Vec::new();
+    assert_eq!(stencil.len(), rom_words.len());
+
+    if let Some(section) = obj_file.section_by_name(".text") {
+        for reloc in &section.relocations {
+            if reloc.kind != RelocationKind::Gprel16 {
+                continue;
+            }
+            let index = (reloc.offset / 4) as usize;
+            let offset = (rom_words[index] & !I_TYPE_MASK) as i16 as i32;
+            let addend = stencil[index].addend as i16 as i32;
+            references.push(GpReference {
+                name: obj_file.symbols[reloc.symbol].name.clone(),
+                filename: filename.to_string(),
+                offset: offset - addend - reloc.addend as i32,
+            });
+        }
+    }
+    references
+}
+
+/// What a reference `offset` bytes into section `index` of an object points to: the symbol there or around it, or
+/// failing that a name made up from `address` in the way splat does. Gives the name, the offset of the symbol itself
+/// and its size.
+pub fn name_in_section(
+    obj_file: &ObjFile,
+    index: usize,
+    offset: u32,
+    address: u32,
+) -> (String, u32, u32) {
+    let defined = obj_file.symbols.iter().filter(|symbol| {
+        // Assembler-local labels like .L12 are as good as anonymous
+        symbol.section == SymbolSection::Section(index)
+            && symbol.is_definition()
+            && !symbol.name.starts_with('.')
+    });
+    let exact = defined.clone().find(|symbol| symbol.address == offset);
+    let containing = defined.clone().find(|symbol| {
+        symbol.address < offset && offset < symbol.address.wrapping_add(symbol.size)
+    });
+    match exact.or(containing) {
+        Some(symbol) => (symbol.name.clone(), symbol.address, symbol.size),
+        None if obj_file.sections[index].kind == SectionKind::Text => {
+            (format!("func_{:08X}", address), offset, 0)
+        }
+        None => (format!("D_{:08X}", address), offset, 0),
+    }
+}
+
+/// Names for what relocations against section symbols in a match point to, e.g. static functions and variables, jump
+/// tables and literals. The section symbols themselves only give where each section starts.
+pub fn parse_section_relative(
+    obj_file: &ObjFile,
+    filename: &str,
+    stencil: &[PreciseStencil],
+    rom_words: &[u32], // Starting from the correct index
+    vram: u32,         // Address of the start of the .text
+) -> Vec<Symbol> {
+    relocated_addresses(obj_file, filename, stencil, rom_words, vram)
+        .into_iter()
+        .filter_map(|relocated| {
+            let symbol = &obj_file.symbols[relocated.symbol];
+            let SymbolSection::Section(target_section) = symbol.section else {
+                return None;
+            };
+            if symbol.kind != SymbolKind::Section || !relocated.complete {
+                return None;
+            }
+            // The addend is how far into the section the address is
+            let (target, offset) = (relocated.target, relocated.addend);
+            let start = target.wrapping_sub(offset);
+            let (name, symbol_offset, size) =
+                name_in_section(obj_file, target_section, offset, target);
+            Some(Symbol::new(
+                &name,
+                start.wrapping_add(symbol_offset),
+                size,
+                filename,
+                true,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_precise_stencil;
+    use crate::objfile::ObjBuilder;
+
+    #[test]
+    fn lo16_pairing() {
+        // lui at, %hi(x); lui v0, %hi(y); lw a0, %lo(x)(at); lw a1, %lo(y)(v0); lw a2, %lo(x+4)(at); lw a3, %lo(z)(at)
+        let obj = ObjBuilder::new("a")
+            .text(&[
+                0x3C010000, 0x3C020000, 0x8C240000, 0x8C450000, 0x8C260004, 0x8C270000,
+            ])
+            .symbol("x", SymbolSection::Undefined, 0, 0)
+            .symbol("y", SymbolSection::Undefined, 0, 0)
+            .symbol("z", SymbolSection::Undefined, 0, 0)
+            .reloc(".text", 0x0, RelocationKind::Hi16, "x", 0)
+            .reloc(".text", 0x4, RelocationKind::Hi16, "y", 0)
+            .reloc(".text", 0x8, RelocationKind::Lo16, "x", 0)
+            .reloc(".text", 0xC, RelocationKind::Lo16, "y", 0)
+            .reloc(".text", 0x10, RelocationKind::Lo16, "x", 0)
+            .reloc(".text", 0x14, RelocationKind::Lo16, "z", 0)
+            .build();
+        let stencil = make_precise_stencil(&obj, &obj.sections[0].data).unwrap();
+        // x at 0x80108000 (so its upper half is rounded up) and y at 0x80200010
+        let rom_words = [
+            0x3C018011, 0x3C028020, 0x8C248000, 0x8C450010, 0x8C268004, 0x8C278000,
+        ];
+
+        let symbols = parse_relocated(&obj, "a", &stencil, &rom_words, 0x80000000)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.address, symbol.complete))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [
+                ("x".to_string(), 0x80108000, true),
+                ("y".to_string(), 0x80200010, true),
+                ("x".to_string(), 0x80108000, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn section_relative_across_other_hi16s() {
+        // lui at, %hi(.data); lui v0, %hi(x); lw a0, %lo(.data+0x10)(at); lw a1, %lo(x)(v0)
+        let obj = ObjBuilder::new("a")
+            .text(&[0x3C010000, 0x3C020000, 0x8C240010, 0x8C450000])
+            .section(".data", SectionKind::Data, &[0; 0x20])
+            .section_symbol(1)
+            .symbol("x", SymbolSection::Undefined, 0, 0)
+            .symbol("table", SymbolSection::Section(1), 0x10, 4)
+            .local()
+            .reloc(".text", 0x0, RelocationKind::Hi16, ".data", 0)
+            .reloc(".text", 0x4, RelocationKind::Hi16, "x", 0)
+            .reloc(".text", 0x8, RelocationKind::Lo16, ".data", 0)
+            .reloc(".text", 0xC, RelocationKind::Lo16, "x", 0)
+            .build();
+        let stencil = make_precise_stencil(&obj, &obj.sections[0].data).unwrap();
+        // .data at 0x80100000
+        let rom_words = [0x3C018010, 0x3C028020, 0x8C240010, 0x8C450000];
+
+        assert_eq!(
+            parse_section_relative(&obj, "a", &stencil, &rom_words, 0x80000000),
+            [Symbol::new("table", 0x80100010, 4, "a", true)]
+        );
+    }
+
+    #[test]
+    fn names_in_section() {
+        let obj = ObjBuilder::new("a")
+            .section(".data", SectionKind::Data, &[0; 0x20])
+            .symbol("table", SymbolSection::Section(0), 0x8, 0x10)
+            .local()
+            .build();
+        assert_eq!(
+            name_in_section(&obj, 0, 0x8, 0x80100008),
+            ("table".to_string(), 0x8, 0x10)
+        );
+        // Into the middle of it, e.g. a field
+        assert_eq!(
+            name_in_section(&obj, 0, 0xC, 0x8010000C),
+            ("table".to_string(), 0x8, 0x10)
+        );
+        assert_eq!(
+            name_in_section(&obj, 0, 0x18, 0x80100018),
+            ("D_80100018".to_string(), 0x18, 0)
+        );
+    }
 }
@@ -0,0 +1,195 @@
+//! Module for turning the symbols recovered from every file into one table. Several names at one address are aliases
+//! of one symbol (or a sign of a bad match), and one name at several addresses is a conflict unless each is a static of
+//! a different file, so both are kept track of rather than one of them being dropped.
+
+use crate::first_max_by_key;
+use crate::symbols::Symbol;
+
+/// A symbol, and the other names found at its address.
+#[derive(Debug, PartialEq)]
+pub struct TableEntry {
+    pub symbol: Symbol,
+    pub aliases: Vec<String>,
+}
+
+/// A name that references put at more than one address.
+#[derive(Debug, PartialEq)]
+pub struct SymbolConflict {
+    pub name: String,
+    pub address: u32,       // The one most references give, which is the one used
+    pub files: Vec<String>, // The files that put it there
+    pub elsewhere: Vec<(u32, Vec<String>)>, // The other addresses, and the files that put it at each
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SymbolTable {
+    pub entries: Vec<TableEntry>, // In address order
+    pub conflicts: Vec<SymbolConflict>,
+}
+
+/// Section symbols only say where one file's section starts, so they stand for nothing outside it.
+fn is_section_symbol(symbol: &Symbol) -> bool {
+    symbol.name.starts_with('.')
+}
+
+fn filenames(symbols: &[&Symbol]) -> Vec<String> {
+    let mut filenames = Vec::new();
+    for symbol in symbols {
+        if !filenames.contains(&symbol.filename) {
+            filenames.push(symbol.filename.clone());
+        }
+    }
+    filenames
+}
+
+/// One symbol from every reference to it: the largest size given, and the defining file if any of them is it.
+fn merge(references: &[&Symbol]) -> Symbol {
+    let first = references
+        .iter()
+        .find(|symbol| symbol.defined)
+        .unwrap_or(&references[0]);
+    let size = references.iter().map(|symbol| symbol.size).max().unwrap();
+    Symbol::new(
+        &first.name,
+        first.address,
+        size,
+        &first.filename,
+        first.defined,
+    )
+}
+
+/// The references to one name, grouped by the address each gives, in the order they come.
+fn by_address<'a>(references: &[&'a Symbol]) -> Vec<(u32, Vec<&'a Symbol>)> {
+    let mut addresses: Vec<(u32, Vec<&Symbol>)> = Vec::new();
+    for symbol in references {
+        match addresses
+            .iter_mut()
+            .find(|(address, _)| *address == symbol.address)
+        {
+            Some((_, at)) => at.push(symbol),
+            None => addresses.push((symbol.address, vec![symbol])),
+        }
+    }
+    addresses
+}
+
+/// Whether the addresses of one name are each a different file's static, i.e. only referred to by the file defining it.
+fn are_statics(addresses: &[(u32, Vec<&Symbol>)]) -> bool {
+    let owners = addresses
+        .iter()
+        .map(|(_, at)| {
+            let owner = &at[0].filename;
+            at.iter()
+                .all(|symbol| symbol.defined && &symbol.filename == owner)
+                .then_some(owner)
+        })
+        .collect::<Option<Vec<_>>>();
+    owners.is_some_and(|owners| {
+        owners
+            .iter()
+            .enumerate()
+            .all(|(i, owner)| !owners[..i].contains(owner))
+    })
+}
+
+impl SymbolTable {
+    pub fn new(symbols: &[Symbol]) -> SymbolTable {
+        let mut table = SymbolTable::default();
+
+        // A HI16 without its LO16 gives only half an address
+        let mut references = symbols
+            .iter()
+            .filter(|symbol| symbol.is_complete())
+            .collect::<Vec<_>>();
+        // Section symbols are kept apart per file
+        references.sort_by(|a, b| {
+            let key = |symbol: &Symbol| {
+                let file = is_section_symbol(symbol).then(|| symbol.filename.clone());
+                (symbol.name.clone(), file)
+            };
+            key(a).cmp(&key(b))
+        });
+
+        let mut named = Vec::new();
+        for same in references.chunk_by(|a, b| {
+            a.name == b.name && (!is_section_symbol(a) || a.filename == b.filename)
+        }) {
+            let addresses = by_address(same);
+            if addresses.len() == 1 || are_statics(&addresses) {
+                named.extend(addresses.iter().map(|(_, at)| merge(at)));
+                continue;
+            }
+
+            let chosen = first_max_by_key(0..addresses.len(), |&i| addresses[i].1.len()).unwrap();
+            named.push(merge(&addresses[chosen].1));
+            table.conflicts.push(SymbolConflict {
+                name: same[0].name.clone(),
+                address: addresses[chosen].0,
+                files: filenames(&addresses[chosen].1),
+                elsewhere: addresses
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != chosen)
+                    .map(|(_, (address, at))| (*address, filenames(at)))
+                    .collect(),
+            });
+        }
+
+        // Names at one address are aliases, with the name the defining file gives preferred
+        named.sort_by_key(|symbol| {
+            (
+                symbol.address,
+                is_section_symbol(symbol),
+                !symbol.defined,
+                std::cmp::Reverse(symbol.size),
+            )
+        });
+        for symbol in named {
+            match table.entries.last_mut() {
+                Some(entry)
+                    if entry.symbol.address == symbol.address
+                        && !is_section_symbol(&entry.symbol)
+                        && !is_section_symbol(&symbol) =>
+                {
+                    entry.aliases.push(symbol.name)
+                }
+                _ => table.entries.push(TableEntry {
+                    symbol,
+                    aliases: Vec::new(),
+                }),
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_and_conflicts() {
+        let table = SymbolTable::new(&[
+            Symbol::new("osGetCount", 0x80001000, 0x8, "getcount", true),
+            Symbol::new("getCount", 0x80001000, 0, "a", false),
+            Symbol::new("__osThing", 0x80002000, 0, "a", false),
+            Symbol::new("__osThing", 0x80002000, 0, "b", false),
+            Symbol::new("__osThing", 0x80003000, 0, "c", false),
+            // Statics of two files
+            Symbol::new("init", 0x80004000, 0, "d", true),
+            Symbol::new("init", 0x80005000, 0, "e", true),
+        ]);
+        assert_eq!(table.entries[0].symbol.name, "osGetCount");
+        assert_eq!(table.entries[0].aliases, ["getCount"]);
+        assert_eq!(table.entries.len(), 4);
+        assert_eq!(
+            table.conflicts,
+            [SymbolConflict {
+                name: "__osThing".to_string(),
+                address: 0x80002000,
+                files: vec!["a".to_string(), "b".to_string()],
+                elsewhere: vec![(0x80003000, vec!["c".to_string()])],
+            }]
+        );
+    }
+}
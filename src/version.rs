@@ -0,0 +1,351 @@
+//! Module for working out which version of a library a binary was linked with, when objects from several versions of
+//! it are searched for at once.
+//!
+//! Most objects are the same in every version, so say nothing. The ones whose .text differs between versions (e.g.
+//! `osInitialize` or `vimgr`) are the evidence: whichever versions' copies were found are the ones the binary could
+//! have been linked with.
+//!
+//! Some games mix versions, e.g. audio from one release and io from another, so once the dominant version is known each
+//! object is still reported from whichever version's copy of it matched best.
+
+use std::collections::HashMap;
+
+use crate::diag::{self, Code};
+use crate::sigs::Signature;
+use crate::{first_max_by_key, SignatureMatch};
+
+/// One object whose .text differs between versions, and which versions' copies of it were found.
+#[derive(Debug, PartialEq)]
+pub struct Evidence {
+    pub object: String,
+    pub matched: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VersionGuess {
+    pub library: Option<String>,
+    pub version: String,
+    pub supporting: usize, // Differing objects found in this version
+    pub total: usize,      // Differing objects found in any version
+    pub runner_up: Option<(String, usize)>,
+    pub evidence: Vec<Evidence>,
+    pub deviations: Vec<(String, String)>, // Objects reported from another version, and the version
+}
+
+impl VersionGuess {
+    /// The fraction of the differing objects that agree with the guess.
+    pub fn confidence(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.supporting as f64 / self.total as f64
+        }
+    }
+
+    pub fn is_tied(&self) -> bool {
+        matches!(&self.runner_up, Some((_, count)) if *count == self.supporting)
+    }
+}
+
+/// Whether any object of `library` was given in more than one version.
+fn has_copies(signatures: &[Signature], library: Option<&str>) -> bool {
+    let mut versions = HashMap::new();
+    signatures.iter().any(|signature| {
+        let Some(version) = signature.version.as_deref() else {
+            return false;
+        };
+        if signature.library.as_deref() != library {
+            return false;
+        }
+        *versions
+            .entry(signature.obj.name.as_str())
+            .or_insert(version)
+            != version
+    })
+}
+
+/// Guess the version of each library that objects from more than one version of were searched for. `matches` is the
+/// result of searching for each of `signatures`.
+///
+/// Objects not tagged with a library are only taken to be versions of one if some object was given from more than one
+/// input, since otherwise their versions are just the unrelated paths they were found through.
+pub fn detect(signatures: &[Signature], matches: &[Option<SignatureMatch>]) -> Vec<VersionGuess> {
+    let mut libraries: Vec<(Option<&str>, Vec<&str>)> = Vec::new();
+    for signature in signatures {
+        let Some(version) = signature.version.as_deref() else {
+            continue;
+        };
+        let library = signature.library.as_deref();
+        match libraries.iter_mut().find(|(name, _)| *name == library) {
+            Some((_, versions)) if !versions.contains(&version) => versions.push(version),
+            Some(_) => (),
+            None => libraries.push((library, vec![version])),
+        }
+    }
+
+    let mut guesses = Vec::new();
+    for (library, versions) in libraries {
+        if versions.len() < 2 || (library.is_none() && !has_copies(signatures, library)) {
+            continue;
+        }
+
+        // Every searched copy of each object, in order of first appearance
+        let mut objects: Vec<(&str, Vec<(&Signature, &SignatureMatch)>)> = Vec::new();
+        for (signature, result) in signatures.iter().zip(matches) {
+            let Some(result) = result else {
+                continue;
+            };
+            if signature.library.as_deref() != library || signature.version.is_none() {
+                continue;
+            }
+            let name = signature.obj.name.as_str();
+            match objects.iter_mut().find(|(object, _)| *object == name) {
+                Some((_, copies)) => copies.push((signature, result)),
+                None => objects.push((name, vec![(signature, result)])),
+            }
+        }
+
+        let mut evidence = Vec::new();
+        for (object, copies) in objects {
+            let differs = copies
+                .iter()
+                .any(|(signature, _)| signature.stencil != copies[0].0.stencil);
+            let mut matched = copies
+                .iter()
+                .filter(|(_, result)| !result.offsets.is_empty())
+                .map(|(signature, _)| signature.version.clone().unwrap())
+                .collect::<Vec<_>>();
+            matched.dedup();
+            if differs && !matched.is_empty() {
+                evidence.push(Evidence {
+                    object: object.to_string(),
+                    matched,
+                });
+            }
+        }
+
+        let mut counts = versions
+            .iter()
+            .map(|version| {
+                let count = evidence
+                    .iter()
+                    .filter(|item| item.matched.iter().any(|matched| matched == version))
+                    .count();
+                (version.to_string(), count)
+            })
+            .collect::<Vec<_>>();
+        // Stable, so ties go to the version given first
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let (version, supporting) = counts.remove(0);
+        let guess = VersionGuess {
+            library: library.map(str::to_string),
+            version,
+            supporting,
+            total: evidence.len(),
+            runner_up: counts.into_iter().next(),
+            evidence,
+            deviations: Vec::new(),
+        };
+
+        let library_name = library.unwrap_or("library");
+        if guess.is_tied() {
+            diag::warning(
+                Code::AmbiguousVersion,
+                None,
+                format_args!(
+                    "could not tell {} versions {} and {} apart, using {}",
+                    library_name,
+                    guess.version,
+                    guess.runner_up.as_ref().unwrap().0,
+                    guess.version
+                ),
+            );
+        } else {
+            diag::info(
+                Code::VersionDetected,
+                None,
+                format_args!(
+                    "{} looks like version {} ({} of {} differing objects)",
+                    library_name, guess.version, guess.supporting, guess.total
+                ),
+            );
+        }
+        guesses.push(guess);
+    }
+    guesses
+}
+
+/// How many bits of the binary a copy's stencil checks. Of two copies that both match, the one that checks more (i.e.
+/// has fewer relocated fields) is the more exact match.
+fn compared_bits(signature: &Signature) -> u32 {
+    signature
+        .stencil
+        .iter()
+        .map(|instr| instr.mask.count_ones())
+        .sum()
+}
+
+/// Choose which objects' results should be reported. For a library whose version was guessed, each object is
+/// reported from the copy that matched most exactly, going with the guessed version if that does not decide it, and
+/// anything reported from another version is added to the guess's deviations. Everything else is reported as is.
+pub fn attribute(
+    guesses: &mut [VersionGuess],
+    signatures: &[Signature],
+    matches: &[Option<SignatureMatch>],
+) -> Vec<bool> {
+    let mut selected = vec![true; signatures.len()];
+
+    for guess in guesses.iter_mut() {
+        // Indices of every copy of each object, in order of first appearance
+        let mut objects: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            if signature.library != guess.library || signature.version.is_none() {
+                continue;
+            }
+            selected[index] = false;
+            let name = signature.obj.name.as_str();
+            match objects.iter_mut().find(|(object, _)| *object == name) {
+                Some((_, copies)) => copies.push(index),
+                None => objects.push((name, vec![index])),
+            }
+        }
+
+        for (object, copies) in objects {
+            let is_guessed =
+                |index: usize| signatures[index].version.as_ref() == Some(&guess.version);
+            let matched = copies
+                .iter()
+                .copied()
+                .filter(
+                    |&index| matches!(&matches[index], Some(result) if !result.offsets.is_empty()),
+                )
+                .collect::<Vec<_>>();
+
+            let chosen = first_max_by_key(matched.iter().copied(), |&index| {
+                (compared_bits(&signatures[index]), is_guessed(index))
+            });
+            let chosen = match chosen {
+                Some(index) => index,
+                None => copies
+                    .iter()
+                    .copied()
+                    .find(|&index| is_guessed(index))
+                    .unwrap_or(copies[0]),
+            };
+
+            selected[chosen] = true;
+            if !is_guessed(chosen) && matched.contains(&chosen) {
+                let version = signatures[chosen].version.clone().unwrap();
+                diag::info(
+                    Code::VersionDeviation,
+                    Some(object),
+                    format_args!("found from {} rather than {}", version, guess.version),
+                );
+                guess.deviations.push((object.to_string(), version));
+            }
+        }
+    }
+
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objfile::ObjBuilder;
+
+    fn signature(name: &str, version: &str, word: u8) -> Signature {
+        // addiu v0, zero, word
+        let obj = ObjBuilder::new(name)
+            .text(&[0x24020000 | word as u32])
+            .build();
+        Signature::new(obj, Some("libultra".to_string()), Some(version.to_string())).unwrap()
+    }
+
+    fn found(found: bool) -> Option<SignatureMatch> {
+        Some(SignatureMatch {
+            offsets: if found { vec![0x1000] } else { Vec::new() },
+            text_size: 4,
+            symbols: Vec::new(),
+            checks: (0, 0),
+            gp_relative: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn picks_version_of_differing_objects() {
+        let signatures = [
+            signature("same", "2.0K", 1),
+            signature("differs", "2.0K", 2),
+            signature("same", "2.0L", 1),
+            signature("differs", "2.0L", 3),
+        ];
+        let matches = [found(true), found(false), found(true), found(true)];
+
+        let guesses = detect(&signatures, &matches);
+        assert_eq!(guesses.len(), 1);
+        assert_eq!(guesses[0].version, "2.0L");
+        assert_eq!((guesses[0].supporting, guesses[0].total), (1, 1));
+        assert_eq!(guesses[0].runner_up, Some(("2.0K".to_string(), 0)));
+    }
+
+    #[test]
+    fn attributes_mixed_versions() {
+        let signatures = [
+            signature("same", "2.0K", 1),
+            signature("audio", "2.0K", 2),
+            signature("io", "2.0K", 4),
+            signature("same", "2.0L", 1),
+            signature("audio", "2.0L", 3),
+            signature("io", "2.0L", 5),
+            signature("extra", "2.0L", 6),
+        ];
+        let matches = [
+            found(true),
+            found(true),
+            found(false),
+            found(true),
+            found(false),
+            found(true),
+            found(false),
+        ];
+
+        let mut guesses = detect(&signatures, &matches);
+        let selected = attribute(&mut guesses, &signatures, &matches);
+        assert_eq!(guesses[0].version, "2.0K"); // Tied, so the first given
+        assert_eq!(selected, [true, true, false, false, false, true, true]);
+        assert_eq!(
+            guesses[0].deviations,
+            [("io".to_string(), "2.0L".to_string())]
+        );
+    }
+
+    #[test]
+    fn single_version_is_not_guessed() {
+        let signatures = [signature("differs", "2.0L", 3)];
+        assert!(detect(&signatures, &[found(true)]).is_empty());
+    }
+
+    #[test]
+    fn untagged_inputs_are_not_versions() {
+        // e.g. `flib game.z64 audio.a io.o`, whose versions are only the names of the inputs
+        let mut signatures = [signature("audio", "audio", 1), signature("io", "io", 2)];
+        for signature in &mut signatures {
+            signature.library = None;
+        }
+        assert!(detect(&signatures, &[found(true), found(false)]).is_empty());
+
+        // Unless the same object was given from more than one of them
+        let mut signatures = [
+            signature("vimgr", "sdk-2.0K", 1),
+            signature("vimgr", "sdk-2.0L", 2),
+        ];
+        for signature in &mut signatures {
+            signature.library = None;
+        }
+        let guesses = detect(&signatures, &[found(false), found(true)]);
+        assert_eq!(guesses.len(), 1);
+        assert_eq!(guesses[0].version, "sdk-2.0L");
+    }
+}